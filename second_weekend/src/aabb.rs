@@ -1,6 +1,7 @@
 use super::ray::Ray;
 use super::vec3::*;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub struct AABB {
     pub min: Vec3,
@@ -12,7 +13,25 @@ impl AABB {
         Self { min, max }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<()> {
+    /// The box around objects without bounds, such as planes.
+    pub fn infinite() -> Self {
+        Self::new(
+            Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        )
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        (0..3).all(|a| self.min[a].is_finite() && self.max[a].is_finite())
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// The part of `t_min..t_max` where the ray is inside the box.
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
         let mut total_t_min = t_min;
         let mut total_t_max = t_max;
         for a in 0..3 {
@@ -33,7 +52,7 @@ impl AABB {
                 return None;
             }
         }
-        Some(())
+        Some((total_t_min, total_t_max))
     }
 }
 
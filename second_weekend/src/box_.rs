@@ -1,11 +1,12 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::aabb::AABB;
 use super::ray::Ray;
 use super::rect::{FlipNormal, XYRect, XZRect, YZRect};
+use super::sampler::Sampler;
 use super::vec3::Vec3;
 use crate::{
-    hitable::{HitRecord, Hitable},
+    hitable::{Crossing, HitRecord, Hitable},
     hitable_list::HitableList,
     material::Material,
 };
@@ -14,10 +15,11 @@ pub struct Box_ {
     pmin: Vec3,
     pmax: Vec3,
     panels: HitableList,
+    material: Arc<dyn Material>,
 }
 
 impl Box_ {
-    pub fn new(p0: Vec3, p1: Vec3, material: Rc<dyn Material>) -> Self {
+    pub fn new(p0: Vec3, p1: Vec3, material: Arc<dyn Material>) -> Self {
         let panels = {
             let panels: Vec<Box<dyn Hitable>> = vec![
                 Box::new(XYRect::new(
@@ -26,7 +28,7 @@ impl Box_ {
                     p0.y(),
                     p1.y(),
                     p1.z(),
-                    Rc::clone(&material),
+                    Arc::clone(&material),
                 )),
                 Box::new(FlipNormal::new(Box::new(XYRect::new(
                     p0.x(),
@@ -34,7 +36,7 @@ impl Box_ {
                     p0.y(),
                     p1.y(),
                     p0.z(),
-                    Rc::clone(&material),
+                    Arc::clone(&material),
                 )))),
                 Box::new(XZRect::new(
                     p0.x(),
@@ -42,7 +44,7 @@ impl Box_ {
                     p0.z(),
                     p1.z(),
                     p1.y(),
-                    Rc::clone(&material),
+                    Arc::clone(&material),
                 )),
                 Box::new(FlipNormal::new(Box::new(XZRect::new(
                     p0.x(),
@@ -50,7 +52,7 @@ impl Box_ {
                     p0.z(),
                     p1.z(),
                     p0.y(),
-                    Rc::clone(&material),
+                    Arc::clone(&material),
                 )))),
                 Box::new(YZRect::new(
                     p0.y(),
@@ -58,7 +60,7 @@ impl Box_ {
                     p0.z(),
                     p1.z(),
                     p1.x(),
-                    Rc::clone(&material),
+                    Arc::clone(&material),
                 )),
                 Box::new(FlipNormal::new(Box::new(YZRect::new(
                     p0.y(),
@@ -66,7 +68,7 @@ impl Box_ {
                     p0.z(),
                     p1.z(),
                     p0.x(),
-                    Rc::clone(&material),
+                    Arc::clone(&material),
                 )))),
             ];
             panels.into_iter().collect::<HitableList>()
@@ -75,6 +77,30 @@ impl Box_ {
             pmin: p0,
             pmax: p1,
             panels,
+            material,
+        }
+    }
+
+    /// Where the line through `r` crosses the face across `axis` at `t`,
+    /// with the face's outward `sign`. Texture coordinates run along the
+    /// other two axes, as on the panels.
+    fn face(&self, r: &Ray, t: f32, axis: usize, sign: f32) -> HitRecord {
+        let p = r.point_at_parameter(t);
+        let (a, b) = match axis {
+            0 => (1, 2),
+            1 => (0, 2),
+            _ => (0, 1),
+        };
+        let along = |i: usize| (p[i] - self.pmin[i]) / (self.pmax[i] - self.pmin[i]);
+        let mut normal = [0.0; 3];
+        normal[axis] = sign;
+        HitRecord {
+            t,
+            p,
+            u: along(a),
+            v: along(b),
+            normal: Vec3::new(normal[0], normal[1], normal[2]),
+            material: Arc::clone(&self.material),
         }
     }
 }
@@ -87,4 +113,58 @@ impl Hitable for Box_ {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
         AABB::new(self.pmin, self.pmax)
     }
+
+    fn is_light(&self) -> bool {
+        self.panels.is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.panels.pdf_value(o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.panels.random(o, sampler)
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    /// The line is inside between the last of its entries into the three
+    /// slabs and the first of its exits.
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        let (o, d) = (r.origin(), r.direction());
+        let mut enter = (-f32::MAX, 0, 0.0);
+        let mut exit = (f32::MAX, 0, 0.0);
+        for axis in 0..3 {
+            if d[axis] == 0.0 {
+                if o[axis] < self.pmin[axis] || self.pmax[axis] < o[axis] {
+                    return Vec::new();
+                }
+                continue;
+            }
+            let t0 = (self.pmin[axis] - o[axis]) / d[axis];
+            let t1 = (self.pmax[axis] - o[axis]) / d[axis];
+            let sign = d[axis].signum();
+            if enter.0 < t0.min(t1) {
+                enter = (t0.min(t1), axis, -sign);
+            }
+            if t0.max(t1) < exit.0 {
+                exit = (t0.max(t1), axis, sign);
+            }
+        }
+        if exit.0 <= enter.0 {
+            return Vec::new();
+        }
+        vec![
+            Crossing {
+                hit: self.face(r, enter.0, enter.1, enter.2),
+                entering: true,
+            },
+            Crossing {
+                hit: self.face(r, exit.0, exit.1, exit.2),
+                entering: false,
+            },
+        ]
+    }
 }
@@ -2,10 +2,54 @@ use std::f32::consts::PI;
 
 use crate::ray::Ray;
 
-use rand::random;
+use super::sampler::Sampler;
 
 use super::vec3::*;
 
+/// The parameters a scene is framed with, before the image aspect is known.
+#[derive(Clone, Debug)]
+pub struct CameraConfig {
+    pub lookfrom: Vec3,
+    pub lookat: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aperture: f32,
+    pub focus_dist: f32,
+    pub time0: f32,
+    pub time1: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            lookfrom: Vec3::new(0.0, 0.0, 0.0),
+            lookat: Vec3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aperture: 0.0,
+            focus_dist: 10.0,
+            time0: 0.0,
+            time1: 1.0,
+        }
+    }
+}
+
+impl CameraConfig {
+    pub fn build(&self, aspect: f32) -> Camera {
+        Camera::new(
+            &self.lookfrom,
+            &self.lookat,
+            &self.vup,
+            self.vfov,
+            aspect,
+            self.aperture,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        )
+    }
+}
+
 pub struct Camera {
     origin: Vec3,
     lower_left_corner: Vec3,
@@ -13,13 +57,13 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
-    w: Vec3,
     time0: f32,
     time1: f32,
     lens_radius: f32,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: &Vec3,
         lookat: &Vec3,
@@ -36,7 +80,7 @@ impl Camera {
         let half_height = (theta / 2.0).tan();
         let half_weight = aspect * half_height;
 
-        let origin = lookfrom.clone();
+        let origin = *lookfrom;
         let w = unit_vector(&(*lookfrom - *lookat));
         let u = unit_vector(&cross(vup, &w));
         let v = cross(&w, &u);
@@ -53,17 +97,16 @@ impl Camera {
             vertical,
             u,
             v,
-            w,
             lens_radius,
             time0,
             time1
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * random_in_unit_disk(sampler.next_2d());
         let offset = self.u * rd.x() + self.v * rd.y();
-        let time = self.time0 + (self.time1 - self.time0) * random::<f32>();
+        let time = self.time0 + (self.time1 - self.time0) * sampler.next_1d();
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical
@@ -73,11 +116,9 @@ impl Camera {
     }
 }
 
-fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let p = Vec3::new(random::<f32>(), random::<f32>(), 0.0) - Vec3::new(1.0, 1.0, 0.0);
-        if 1.0 < dot(&p, &p) {
-            return p;
-        }
-    }
+/// Maps a point of the unit square to the unit disk, preserving area.
+fn random_in_unit_disk((u1, u2): (f32, f32)) -> Vec3 {
+    let r = u1.sqrt();
+    let theta = 2.0 * PI * u2;
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
 }
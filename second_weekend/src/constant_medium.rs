@@ -1,21 +1,26 @@
-use std::rc::Rc;
-
-use rand::random;
+use std::sync::Arc;
 
 use super::aabb::AABB;
 use super::hitable::{HitRecord, Hitable};
 use super::material::Material;
+use super::pdf::uniform_sphere_direction;
 use super::ray::Ray;
-use super::vec3::Vec3;
+use super::sampler::{Independent, Sampler};
 
+/// Volume of uniform density inside `boundary`.
+///
+/// Hits inside the volume carry a random normal, so that a material
+/// scattering around its normal scatters isotropically on average. The
+/// distance into the volume and the normal are drawn from the sampler given
+/// to `hit_sampled`.
 pub struct ConstantMedium {
     boundary: Box<dyn Hitable>,
     density: f32,
-    material: Rc<dyn Material>,
+    material: Arc<dyn Material>,
 }
 
 impl ConstantMedium {
-    pub fn new(density: f32, material: Rc<dyn Material>, boundary: Box<dyn Hitable>) -> Self {
+    pub fn new(density: f32, material: Arc<dyn Material>, boundary: Box<dyn Hitable>) -> Self {
         Self {
             density,
             boundary,
@@ -26,8 +31,18 @@ impl ConstantMedium {
 
 impl Hitable for ConstantMedium {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let rec1 = self.boundary.hit(r, -std::f32::MAX, std::f32::MAX)?;
-        let rec2 = self.boundary.hit(r, rec1.t + 0.00001, std::f32::MAX)?;
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        let rec1 = self.boundary.hit(r, -f32::MAX, f32::MAX)?;
+        let rec2 = self.boundary.hit(r, rec1.t + 0.00001, f32::MAX)?;
         let t1 = rec1.t.max(t_min);
         let t2 = rec2.t.min(t_max);
         if t2 <= t1 {
@@ -36,7 +51,7 @@ impl Hitable for ConstantMedium {
 
         let t1 = t1.max(0.0);
         let distance_inside_boundary = (t2 - t1) * r.direction().lenght();
-        let hit_distance = -(1.0 / self.density) * random::<f32>().ln();
+        let hit_distance = -(1.0 / self.density) * (1.0 - sampler.next_1d()).ln();
         if distance_inside_boundary <= hit_distance {
             return None;
         }
@@ -48,8 +63,8 @@ impl Hitable for ConstantMedium {
             p,
             u: 0.0,
             v: 0.0,
-            normal: Vec3::default(),
-            material: Rc::clone(&self.material),
+            normal: uniform_sphere_direction(sampler.next_2d()),
+            material: Arc::clone(&self.material),
         })
     }
 
@@ -57,3 +72,46 @@ impl Hitable for ConstantMedium {
         self.boundary.bounding_box(t0, t1)
     }
 }
+
+#[test]
+fn test_free_flight_follows_the_sampler() {
+    use super::box_::Box_;
+    use super::hitable_list::HitableList;
+    use super::material::Dielectric;
+    use super::rect::Translate;
+    use super::sampler::SamplerKind;
+    use super::vec3::Vec3;
+
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let cube = Box_::new(
+        Vec3::default(),
+        Vec3::new(1.0, 1.0, 1.0),
+        Arc::clone(&glass),
+    );
+    let smoke = ConstantMedium::new(1.0, glass, Box::new(cube));
+    let world = vec![
+        Box::new(Translate::new(Vec3::new(0.0, 0.0, 2.0), Box::new(smoke))) as Box<dyn Hitable>,
+    ]
+    .into_iter()
+    .collect::<HitableList>();
+
+    // A unit of optical depth lets through 1/e of the rays. A sampler spreads
+    // the distances out evenly, so the fraction is close even for few rays.
+    let r = Ray::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+    let samples = 256;
+    let mut sampler = SamplerKind::Sobol.build(3, samples);
+    let mut passed = 0;
+    for s in 0..samples {
+        sampler.start_sample(s);
+        match world.hit_sampled(&r, 0.001, f32::MAX, sampler.as_mut()) {
+            Some(rec) => assert!(2.0 <= rec.p.z() && rec.p.z() <= 3.0),
+            None => passed += 1,
+        }
+    }
+    let transmittance = passed as f32 / samples as f32;
+    assert!(
+        (transmittance - (-1.0f32).exp()).abs() < 0.01,
+        "{}",
+        transmittance
+    );
+}
@@ -0,0 +1,264 @@
+use super::aabb::{surrounding_box, AABB};
+use super::hitable::{Crossing, HitRecord, Hitable};
+use super::ray::Ray;
+use super::vec3::Vec3;
+
+/// How a `Csg` combines the insides of its two solids.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOp {
+    /// Inside either solid.
+    Union,
+    /// Inside both solids.
+    Intersection,
+    /// Inside the first solid but not the second.
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, a: bool, b: bool) -> bool {
+        match self {
+            CsgOp::Union => a || b,
+            CsgOp::Intersection => a && b,
+            CsgOp::Difference => a && !b,
+        }
+    }
+}
+
+fn starts_inside(crossings: &[Crossing]) -> bool {
+    matches!(crossings.first(), Some(c) if !c.entering)
+}
+
+/// A solid built from two closed objects, such as a lens from the
+/// intersection of two spheres or a hollowed shape from a difference.
+///
+/// Both objects are crossed along the whole line of a ray, and the
+/// crossings where the combined inside changes become the solid's surface.
+/// Each keeps the material of the object it lies on.
+///
+/// Both objects should be solids, see `Hitable::is_solid`. Open surfaces,
+/// meshes and media have no inside to combine.
+pub struct Csg {
+    op: CsgOp,
+    a: Box<dyn Hitable>,
+    b: Box<dyn Hitable>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Box<dyn Hitable>, b: Box<dyn Hitable>) -> Self {
+        Self { op, a, b }
+    }
+}
+
+impl Hitable for Csg {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.bounding_box(r.time, r.time).hit(r, t_min, t_max)?;
+        self.crossings(r)
+            .into_iter()
+            .map(|c| c.hit)
+            .find(|hit| t_min < hit.t && hit.t < t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
+        let a = self.a.bounding_box(t0, t1);
+        let b = self.b.bounding_box(t0, t1);
+        match self.op {
+            CsgOp::Union => surrounding_box(&a, &b),
+            CsgOp::Intersection => AABB::new(
+                Vec3::new(
+                    a.min.x().max(b.min.x()),
+                    a.min.y().max(b.min.y()),
+                    a.min.z().max(b.min.z()),
+                ),
+                Vec3::new(
+                    a.max.x().min(b.max.x()),
+                    a.max.y().min(b.max.y()),
+                    a.max.z().min(b.max.z()),
+                ),
+            ),
+            CsgOp::Difference => a,
+        }
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        let a = self.a.crossings(r);
+        let b = self.b.crossings(r);
+        let mut in_a = starts_inside(&a);
+        let mut in_b = starts_inside(&b);
+        let mut inside = self.op.inside(in_a, in_b);
+
+        let mut a = a.into_iter().peekable();
+        let mut b = b.into_iter().peekable();
+        let mut crossings = Vec::new();
+        loop {
+            let from_a = match (a.peek(), b.peek()) {
+                (Some(ca), Some(cb)) => ca.hit.t <= cb.hit.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let mut crossing = if from_a {
+                let crossing = a.next().unwrap();
+                in_a = crossing.entering;
+                crossing
+            } else {
+                let crossing = b.next().unwrap();
+                in_b = crossing.entering;
+                crossing
+            };
+            let now_inside = self.op.inside(in_a, in_b);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+            // The subtracted object's surface faces into the solid.
+            if self.op == CsgOp::Difference && !from_a {
+                crossing.hit.normal = -crossing.hit.normal;
+            }
+            crossing.entering = now_inside;
+            crossings.push(crossing);
+        }
+        crossings
+    }
+}
+
+#[test]
+fn test_csg() {
+    use super::box_::Box_;
+    use super::cylinder::Cone;
+    use super::material::{Dielectric, Material};
+    use super::sphere::Sphere;
+    use super::torus::Torus;
+    use super::transform::{Matrix4, Transform};
+    use std::sync::Arc;
+
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let sphere = |x: f32, radius: f32| -> Box<dyn Hitable> {
+        Box::new(Sphere::new(
+            Vec3::new(x, 0.0, 0.0),
+            radius,
+            Arc::clone(&glass),
+        ))
+    };
+    let along_x = |x: f32| Ray::new(Vec3::new(x, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    let ts = |hitable: &dyn Hitable, r: &Ray| {
+        hitable
+            .crossings(r)
+            .iter()
+            .map(|c| (c.hit.t, c.entering))
+            .collect::<Vec<_>>()
+    };
+
+    // Spheres over -2..2 and 1..5 along the x axis.
+    let union = Csg::new(CsgOp::Union, sphere(0.0, 2.0), sphere(3.0, 2.0));
+    assert_eq!(ts(&union, &along_x(-10.0)), [(8.0, true), (15.0, false)]);
+    let lens = Csg::new(CsgOp::Intersection, sphere(0.0, 2.0), sphere(3.0, 2.0));
+    assert_eq!(ts(&lens, &along_x(-10.0)), [(11.0, true), (12.0, false)]);
+    let bitten = Csg::new(CsgOp::Difference, sphere(0.0, 2.0), sphere(3.0, 2.0));
+    assert_eq!(ts(&bitten, &along_x(-10.0)), [(8.0, true), (11.0, false)]);
+
+    // The bite's surface faces out of what is left, towards +x.
+    let back = Ray::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+    let rec = bitten.hit(&back, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 9.0).abs() < 1e-5 && rec.normal.x() == 1.0);
+    // A ray starting inside the lens, as a refracted one would, leaves it.
+    let rec = lens.hit(&along_x(1.5), 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 0.5).abs() < 1e-5 && rec.normal.x() == 1.0);
+
+    // A box with a spherical hollow, nested inside another operation.
+    let hollow = Csg::new(
+        CsgOp::Difference,
+        Box::new(Box_::new(
+            Vec3::new(-3.0, -3.0, -3.0),
+            Vec3::new(3.0, 3.0, 3.0),
+            Arc::clone(&glass),
+        )),
+        sphere(0.0, 2.0),
+    );
+    let crossed = ts(&hollow, &along_x(-10.0));
+    let expected = [(7.0, true), (8.0, false), (12.0, true), (13.0, false)];
+    assert_eq!(crossed.len(), expected.len());
+    for ((t, entering), (e, e_entering)) in crossed.iter().zip(expected) {
+        assert!((t - e).abs() < 1e-4 && *entering == e_entering);
+    }
+    let notched = Csg::new(CsgOp::Difference, Box::new(hollow), sphere(-3.0, 0.5));
+    let rec = notched.hit(&along_x(-10.0), 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 7.5).abs() < 1e-4 && rec.normal.x() == -1.0);
+
+    // A thin wall far along the ray keeps both of its crossings, also when
+    // placed by a transform.
+    let wall = || {
+        Box_::new(
+            Vec3::new(1000.0, -1.0, -1.0),
+            Vec3::new(1000.01, 1.0, 1.0),
+            Arc::clone(&glass),
+        )
+    };
+    let crossed = ts(&wall(), &along_x(0.0));
+    assert_eq!(crossed.len(), 2);
+    assert!(crossed[0].1 && !crossed[1].1 && crossed[0].0 < crossed[1].0);
+    let moved = Transform::new(
+        Matrix4::translate(&Vec3::new(-990.0, 0.0, 0.0)),
+        Arc::new(wall()),
+    );
+    let crossed = ts(&moved, &along_x(0.0));
+    assert_eq!(crossed.len(), 2);
+    assert!((crossed[0].0 - 10.0).abs() < 1e-3 && crossed[0].1);
+
+    // A line through a torus's hole crosses its tube twice on each side.
+    let ring = Torus::new(
+        Vec3::default(),
+        Vec3::new(0.0, 1.0, 0.0),
+        2.0,
+        0.5,
+        Arc::clone(&glass),
+    );
+    let crossed = ts(&ring, &along_x(-10.0));
+    let entering = crossed.iter().map(|c| c.1).collect::<Vec<_>>();
+    assert_eq!(entering, [true, false, true, false]);
+
+    // Lines that only touch a surface, or run along a cone's slope, keep
+    // the crossings of a solid they pass through afterwards intact.
+    let ball = |center: Vec3| -> Box<dyn Hitable> {
+        Box::new(Sphere::new(center, 0.5, Arc::clone(&glass)))
+    };
+    let grazing = Ray::new(Vec3::new(-10.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    assert!(ring.crossings(&grazing).is_empty());
+    let with_ring = Csg::new(CsgOp::Union, Box::new(ring), ball(Vec3::new(6.0, 0.5, 0.0)));
+    let crossed = ts(&with_ring, &grazing);
+    assert_eq!(crossed.len(), 2, "{:?}", crossed);
+    assert!(crossed[0].1 && !crossed[1].1);
+    let cone = Cone::new(
+        Vec3::default(),
+        Vec3::new(0.0, 2.0, 0.0),
+        1.0,
+        true,
+        Arc::clone(&glass),
+    );
+    let slope = Ray::new(Vec3::new(0.5, -1.0, 0.0), Vec3::new(-1.0, 2.0, 0.0), 0.0);
+    assert_eq!(ts(&cone, &slope), [(0.5, true), (1.0, false)]);
+    let with_cone = Csg::new(
+        CsgOp::Union,
+        Box::new(cone),
+        ball(Vec3::new(-2.5, 5.0, 0.0)),
+    );
+    let crossed = ts(&with_cone, &slope);
+    let offset = 0.5 / 5.0f32.sqrt();
+    let expected = [
+        (0.5, true),
+        (1.0, false),
+        (3.0 - offset, true),
+        (3.0 + offset, false),
+    ];
+    assert_eq!(crossed.len(), expected.len());
+    for ((t, entering), (e, e_entering)) in crossed.iter().zip(expected) {
+        assert!(
+            (t - e).abs() < 1e-4 && *entering == e_entering,
+            "{:?}",
+            crossed
+        );
+    }
+}
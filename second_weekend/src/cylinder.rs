@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use super::aabb::{surrounding_box, AABB};
+use super::hitable::{Crossing, HitRecord, Hitable};
+use super::material::Material;
+use super::onb::Onb;
+use super::plane::{azimuth, disk_bounds};
+use super::ray::Ray;
+use super::vec3::*;
+
+/// Shape around the segment from `p0` to `p1`, with its own coordinates:
+/// `z` runs along the segment from 0 to `height`.
+struct Axis {
+    p0: Vec3,
+    frame: Onb,
+    height: f32,
+}
+
+impl Axis {
+    fn new(p0: Vec3, p1: Vec3) -> Self {
+        debug_assert!(0.0 < (p1 - p0).squared_length(), "axis has no length");
+        Self {
+            p0,
+            frame: Onb::from_w(&(p1 - p0)),
+            height: (p1 - p0).lenght(),
+        }
+    }
+
+    /// The ray's origin and direction in local coordinates.
+    fn local_ray(&self, r: &Ray) -> (Vec3, Vec3) {
+        (
+            self.frame.coords(&(*r.origin() - self.p0)),
+            self.frame.coords(r.direction()),
+        )
+    }
+}
+
+/// A point where the line meets the surface, as `t`, the local normal and
+/// texture coordinates.
+type SurfaceHit = (f32, Vec3, f32, f32);
+
+/// Where the line crosses the cap at height `z`, a disk of `radius` facing
+/// `normal_z`.
+fn cap((o, d): (Vec3, Vec3), z: f32, radius: f32, normal_z: f32) -> Option<SurfaceHit> {
+    if d.z() == 0.0 {
+        return None;
+    }
+    let t = (z - o.z()) / d.z();
+    let p = o + t * d;
+    let dist = (p.x() * p.x() + p.y() * p.y()).sqrt();
+    if radius < dist {
+        return None;
+    }
+    Some((t, Vec3::new(0.0, 0.0, normal_z), azimuth(&p), dist / radius))
+}
+
+/// Roots of `a t^2 + b t + c` where it changes sign, nearest first. A
+/// double root only touches the surface and is left out.
+fn quadratic(a: f32, b: f32, c: f32) -> Vec<f32> {
+    if a.abs() < 1e-12 {
+        if b == 0.0 {
+            return Vec::new();
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant <= 0.0 {
+        return Vec::new();
+    }
+    // Avoids cancelling the larger root against `b`.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = (q / a, c / q);
+    vec![t0.min(t1), t0.max(t1)]
+}
+
+/// Where the roots of the side's quadratic lie between the two ends, with
+/// the side's local normal there.
+fn side(
+    (o, d): (Vec3, Vec3),
+    h: f32,
+    roots: Vec<f32>,
+    normal: impl Fn(&Vec3) -> Vec3,
+) -> Vec<SurfaceHit> {
+    roots
+        .into_iter()
+        .map(|t| (t, o + t * d))
+        .filter(|(_, p)| 0.0 <= p.z() && p.z() <= h)
+        .map(|(t, p)| (t, normal(&p), azimuth(&p), p.z() / h))
+        .collect()
+}
+
+fn record(
+    axis: &Axis,
+    r: &Ray,
+    (t, normal, u, v): SurfaceHit,
+    material: &Arc<dyn Material>,
+) -> HitRecord {
+    HitRecord {
+        t,
+        p: r.point_at_parameter(t),
+        u,
+        v,
+        normal: unit_vector(&axis.frame.local(&normal)),
+        material: Arc::clone(material),
+    }
+}
+
+fn nearest(
+    axis: &Axis,
+    r: &Ray,
+    hits: Vec<SurfaceHit>,
+    t_min: f32,
+    t_max: f32,
+    material: &Arc<dyn Material>,
+) -> Option<HitRecord> {
+    hits.into_iter()
+        .filter(|&(t, ..)| t_min < t && t < t_max)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|hit| record(axis, r, hit, material))
+}
+
+fn crossings(
+    axis: &Axis,
+    r: &Ray,
+    mut hits: Vec<SurfaceHit>,
+    material: &Arc<dyn Material>,
+) -> Vec<Crossing> {
+    hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+    // A line through the rim meets the side and a cap at the same point.
+    hits.dedup_by(|b, a| a.0 == b.0);
+    hits.into_iter()
+        .map(|hit| {
+            let hit = record(axis, r, hit, material);
+            let entering = dot(&hit.normal, r.direction()) < 0.0;
+            Crossing { hit, entering }
+        })
+        .collect()
+}
+
+/// A cylinder of `radius` from `p0` to `p1`, closed by disks at both ends
+/// when `capped`.
+pub struct Cylinder {
+    axis: Axis,
+    radius: f32,
+    capped: bool,
+    material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(p0: Vec3, p1: Vec3, radius: f32, capped: bool, material: Arc<dyn Material>) -> Self {
+        Self {
+            axis: Axis::new(p0, p1),
+            radius,
+            capped,
+            material,
+        }
+    }
+}
+
+impl Cylinder {
+    /// Everywhere the line through `r` meets the surface, in any order.
+    fn surface(&self, r: &Ray) -> Vec<SurfaceHit> {
+        let (o, d) = self.axis.local_ray(r);
+        let h = self.axis.height;
+        let roots = quadratic(
+            d.x() * d.x() + d.y() * d.y(),
+            2.0 * (o.x() * d.x() + o.y() * d.y()),
+            o.x() * o.x() + o.y() * o.y() - self.radius * self.radius,
+        );
+        let mut hits = side((o, d), h, roots, |p| Vec3::new(p.x(), p.y(), 0.0));
+        if self.capped {
+            hits.extend(cap((o, d), 0.0, self.radius, -1.0));
+            hits.extend(cap((o, d), h, self.radius, 1.0));
+        }
+        hits
+    }
+}
+
+impl Hitable for Cylinder {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        nearest(&self.axis, r, self.surface(r), t_min, t_max, &self.material)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.capped
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        crossings(&self.axis, r, self.surface(r), &self.material)
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        let w = self.axis.frame.w();
+        let p1 = self.axis.p0 + self.axis.height * w;
+        surrounding_box(
+            &disk_bounds(&self.axis.p0, &w, self.radius),
+            &disk_bounds(&p1, &w, self.radius),
+        )
+    }
+}
+
+/// A cone with a base of `radius` around `base`, narrowing to `apex`, closed
+/// by its base when `capped`.
+pub struct Cone {
+    axis: Axis,
+    radius: f32,
+    capped: bool,
+    material: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(
+        base: Vec3,
+        apex: Vec3,
+        radius: f32,
+        capped: bool,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            axis: Axis::new(base, apex),
+            radius,
+            capped,
+            material,
+        }
+    }
+}
+
+impl Cone {
+    /// Everywhere the line through `r` meets the surface, in any order.
+    fn surface(&self, r: &Ray) -> Vec<SurfaceHit> {
+        let (o, d) = self.axis.local_ray(r);
+        let h = self.axis.height;
+        // The side is x^2 + y^2 = k^2 (h - z)^2.
+        let k2 = (self.radius / h) * (self.radius / h);
+        let roots = quadratic(
+            d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z(),
+            2.0 * (o.x() * d.x() + o.y() * d.y() + k2 * (h - o.z()) * d.z()),
+            o.x() * o.x() + o.y() * o.y() - k2 * (h - o.z()) * (h - o.z()),
+        );
+        let mut hits = side((o, d), h, roots, |p| {
+            Vec3::new(p.x(), p.y(), k2 * (h - p.z()))
+        });
+        if self.capped {
+            hits.extend(cap((o, d), 0.0, self.radius, -1.0));
+        }
+        hits
+    }
+}
+
+impl Hitable for Cone {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        nearest(&self.axis, r, self.surface(r), t_min, t_max, &self.material)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.capped
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        crossings(&self.axis, r, self.surface(r), &self.material)
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        let w = self.axis.frame.w();
+        let apex = self.axis.p0 + self.axis.height * w;
+        surrounding_box(
+            &disk_bounds(&self.axis.p0, &w, self.radius),
+            &AABB::new(apex, apex),
+        )
+    }
+}
+
+#[test]
+fn test_cylinder_and_cone() {
+    use super::material::Dielectric;
+
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    // Standing on the ground, up the y axis.
+    let p0 = Vec3::new(0.0, 0.0, 0.0);
+    let p1 = Vec3::new(0.0, 2.0, 0.0);
+    let open = Cylinder::new(p0, p1, 1.0, false, Arc::clone(&glass));
+    let closed = Cylinder::new(p0, p1, 1.0, true, Arc::clone(&glass));
+    let cone = Cone::new(p0, p1, 1.0, true, glass);
+
+    let side = Ray::new(Vec3::new(5.0, 1.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+    let rec = open.hit(&side, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.0).abs() < 1e-5 && (rec.normal.x() - 1.0).abs() < 1e-5);
+    assert!((rec.v - 0.5).abs() < 1e-5);
+    // Halfway up, the cone is half as wide and its side leans back by 45
+    // degrees from the 2:1 slope.
+    let rec = cone.hit(&side, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.5).abs() < 1e-5, "{}", rec.t);
+    let slope = Vec3::new(2.0, 1.0, 0.0);
+    assert!((dot(&rec.normal, &unit_vector(&slope)) - 1.0).abs() < 1e-5);
+
+    // From above, the open cylinder is seen through to its far wall's
+    // inside, while the closed one stops at the lid.
+    let slanted = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.5, -2.0, 0.0), 0.0);
+    let wall = open.hit(&slanted, 0.001, f32::MAX).unwrap();
+    assert!((wall.t - 2.0).abs() < 1e-5 && (wall.p.y() - 1.0).abs() < 1e-5);
+    assert!(0.0 < dot(&wall.normal, slanted.direction()));
+    let lid = closed.hit(&slanted, 0.001, f32::MAX).unwrap();
+    assert!((lid.t - 1.5).abs() < 1e-5);
+    // Straight down, the open one never reaches its wall.
+    let down = Ray::new(Vec3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    assert!(open.hit(&down, 0.001, f32::MAX).is_none());
+    let lid = closed.hit(&down, 0.001, f32::MAX).unwrap();
+    assert!((lid.t - 3.0).abs() < 1e-5 && (lid.normal.y() - 1.0).abs() < 1e-5);
+    let up = Ray::new(Vec3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+    let bottom = cone.hit(&up, 0.001, f32::MAX).unwrap();
+    assert!((bottom.t - 5.0).abs() < 1e-5 && (bottom.normal.y() + 1.0).abs() < 1e-5);
+
+    let b = closed.bounding_box(0.0, 1.0);
+    assert!((b.min.x() + 1.0).abs() < 1e-3 && (b.max.y() - 2.0).abs() < 1e-3);
+    let tilted = Cone::new(
+        p0,
+        Vec3::new(3.0, 0.0, 0.0),
+        1.0,
+        false,
+        Arc::clone(&closed.material),
+    );
+    let b = tilted.bounding_box(0.0, 1.0);
+    assert!((b.max.x() - 3.0).abs() < 1e-3 && (b.max.z() - 1.0).abs() < 1e-3);
+}
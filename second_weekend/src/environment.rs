@@ -0,0 +1,291 @@
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::hdr::HdrDecoder;
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ImageError, ImageResult};
+
+use super::pdf::uniform_sphere_direction;
+use super::sampler::Sampler;
+use super::vec3::*;
+
+/// Light arriving from infinitely far away, seen by rays that leave the
+/// scene.
+pub trait Environment: Send + Sync {
+    fn value(&self, direction: &Vec3) -> Vec3;
+
+    /// Whether the environment lights the scene, so that `sample` is worth
+    /// drawing shadow rays towards.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// Density, with respect to solid angle, of `sample` returning
+    /// `direction`.
+    fn pdf_value(&self, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        uniform_sphere_direction(sampler.next_2d())
+    }
+}
+
+/// The same color in every direction.
+pub struct Constant {
+    color: Vec3,
+}
+
+impl Constant {
+    pub fn new(color: Vec3) -> Self {
+        Self { color }
+    }
+}
+
+impl Environment for Constant {
+    fn value(&self, _direction: &Vec3) -> Vec3 {
+        self.color
+    }
+
+    fn is_light(&self) -> bool {
+        0.0 < self.color.x().max(self.color.y()).max(self.color.z())
+    }
+}
+
+/// Blends from `horizon` straight down to `zenith` straight up, like the
+/// sky of the first book.
+pub struct Sky {
+    horizon: Vec3,
+    zenith: Vec3,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            horizon: Vec3::new(1.0, 1.0, 1.0),
+            zenith: Vec3::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Environment for Sky {
+    fn value(&self, direction: &Vec3) -> Vec3 {
+        let t = 0.5 * (unit_vector(direction).y() + 1.0);
+        (1.0 - t) * self.horizon + t * self.zenith
+    }
+
+    fn is_light(&self) -> bool {
+        true
+    }
+}
+
+/// Piecewise constant density over [0, 1), one piece per entry of `func`.
+struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    fn new(func: Vec<f32>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f32;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            // Fall back to a uniform density when every piece is black.
+            *c = if 0.0 < integral {
+                *c / integral
+            } else {
+                i as f32 / n as f32
+            };
+        }
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    fn pdf(&self, i: usize) -> f32 {
+        if 0.0 < self.integral {
+            self.func[i] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    /// Maps `u` to a point in [0, 1) and the piece it falls in.
+    fn sample(&self, u: f32) -> (f32, usize) {
+        let n = self.func.len();
+        let i = (self.cdf.partition_point(|&c| c <= u) - 1).min(n - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let du = if 0.0 < width {
+            (u - self.cdf[i]) / width
+        } else {
+            0.0
+        };
+        (((i as f32 + du) / n as f32).min(1.0 - f32::EPSILON), i)
+    }
+}
+
+fn luminance(c: &Vec3) -> f32 {
+    0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b()
+}
+
+/// A latitude-longitude image of the surroundings, turned by `rotation`
+/// around the y axis and scaled by `intensity`.
+///
+/// Directions are sampled in proportion to the luminance of the pixels.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    rotation: f32,
+    intensity: f32,
+    rows: Distribution1D,
+    columns: Vec<Distribution1D>,
+}
+
+impl EnvironmentMap {
+    /// `rotation` is in degrees. Panics if the map has no pixels.
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: Vec<Vec3>,
+        rotation: f32,
+        intensity: f32,
+    ) -> Self {
+        assert!(0 < width && 0 < height, "empty environment map");
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        let columns = (0..height)
+            .map(|y| {
+                // Rows near the poles cover less of the sphere.
+                let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+                let row = &pixels[y * width..(y + 1) * width];
+                Distribution1D::new(row.iter().map(|c| luminance(c) * sin_theta).collect())
+            })
+            .collect::<Vec<_>>();
+        let rows = Distribution1D::new(columns.iter().map(|c| c.integral).collect());
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: rotation.to_radians(),
+            intensity,
+            rows,
+            columns,
+        }
+    }
+
+    /// Reads a Radiance .hdr image.
+    pub fn load(path: &Path, rotation: f32, intensity: f32) -> ImageResult<Self> {
+        let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+        let meta = decoder.metadata();
+        if meta.width == 0 || meta.height == 0 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let pixels = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .map(|p| Vec3::new(p[0], p[1], p[2]))
+            .collect();
+        Ok(Self::new(
+            meta.width as usize,
+            meta.height as usize,
+            pixels,
+            rotation,
+            intensity,
+        ))
+    }
+
+    fn to_uv(&self, direction: &Vec3) -> (f32, f32) {
+        let d = unit_vector(direction);
+        let phi = d.z().atan2(d.x()) - self.rotation;
+        let u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = d.y().clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn pixel(&self, u: f32, v: f32) -> (usize, usize) {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        (x, y)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn value(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.to_uv(direction);
+        let (x, y) = self.pixel(u, v);
+        self.intensity * self.pixels[y * self.width + x]
+    }
+
+    fn is_light(&self) -> bool {
+        true
+    }
+
+    fn pdf_value(&self, direction: &Vec3) -> f32 {
+        let (u, v) = self.to_uv(direction);
+        let (x, y) = self.pixel(u, v);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // Density over the image, changed to solid angle.
+        self.rows.pdf(y) * self.columns[y].pdf(x) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u1, u2) = sampler.next_2d();
+        let (v, y) = self.rows.sample(u2);
+        let (u, _) = self.columns[y].sample(u1);
+        let theta = PI * v;
+        let phi = 2.0 * PI * u + self.rotation;
+        Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+}
+
+#[test]
+fn test_environment_map_sampling() {
+    use super::pdf::{random_unit_vector, Pdf};
+    use super::rng::reseed;
+    use super::sampler::Independent;
+
+    reseed(5);
+    // Dim everywhere but for a bright patch.
+    let (width, height) = (16, 8);
+    let mut pixels = vec![Vec3::new(0.1, 0.1, 0.1); width * height];
+    pixels[2 * width + 5] = Vec3::new(500.0, 400.0, 300.0);
+    let map = EnvironmentMap::new(width, height, pixels, 30.0, 2.0);
+    let pdf = super::pdf::EnvironmentPdf::new(&map);
+
+    let n = 200_000;
+    let integral = (0..n)
+        .map(|_| pdf.value(&random_unit_vector()))
+        .sum::<f32>()
+        * 4.0
+        * PI
+        / n as f32;
+    assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+
+    // Most samples land on the patch, and agree with the density.
+    let bright = (0..1000)
+        .filter(|_| {
+            let d = pdf.generate(&mut Independent);
+            assert!(0.0 < pdf.value(&d));
+            map.value(&d).x() == 1000.0
+        })
+        .count();
+    assert!(900 < bright, "{} samples on the patch", bright);
+}
@@ -0,0 +1,283 @@
+use std::f32::consts::PI;
+
+use super::output::Framebuffer;
+use super::vec3::Vec3;
+
+/// Least summed weight a pixel needs to be averaged rather than left black.
+const MIN_WEIGHT: f32 = 1e-6;
+
+/// Reconstruction filter that spreads each sample over the pixels around it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+    /// Every sample counts only for the pixel it fell in.
+    #[default]
+    Box,
+    /// Weight falling off linearly over one pixel.
+    Tent,
+    /// Truncated Gaussian with a standard deviation of half a pixel.
+    Gaussian,
+    /// Mitchell-Netravali cubic with B = C = 1/3.
+    Mitchell,
+    /// Sinc windowed by a wider sinc over three pixels.
+    Lanczos,
+}
+
+fn gaussian(x: f32, radius: f32) -> f32 {
+    let sigma = 0.5;
+    let g = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+    (g(x) - g(radius)).max(0.0)
+}
+
+fn mitchell(x: f32) -> f32 {
+    let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+    let x = x.abs();
+    if 2.0 <= x {
+        0.0
+    } else if 1.0 <= x {
+        ((-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b))
+            / 6.0
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter {
+    /// How many pixels away from a sample the filter still reaches.
+    pub fn radius(self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+            Filter::Lanczos => 3.0,
+        }
+    }
+
+    fn eval_1d(self, x: f32) -> f32 {
+        let radius = self.radius();
+        // Half open, so that a sample on the border between two pixels only
+        // counts for the one it fell in.
+        if x <= -radius || radius < x {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - x.abs(),
+            Filter::Gaussian => gaussian(x, radius),
+            Filter::Mitchell => mitchell(x),
+            Filter::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+
+    /// Weight for a pixel whose center lies `(dx, dy)` pixels from a sample.
+    /// May be negative for the filters with lobes.
+    pub fn eval(self, dx: f32, dy: f32) -> f32 {
+        self.eval_1d(dx) * self.eval_1d(dy)
+    }
+}
+
+/// Weighted sums of the samples splatted onto part of the image.
+///
+/// A tile covers the pixels its samples are taken in plus a margin as wide
+/// as the filter, so tiles can be filled independently and merged later.
+pub struct FilmTile {
+    filter: Filter,
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+    sum: Vec<Vec3>,
+    weight: Vec<f32>,
+}
+
+impl FilmTile {
+    /// Splats `radiance` seen at image position `(px, py)`, measured in
+    /// pixels from the top left corner.
+    pub fn add_sample(&mut self, px: f32, py: f32, radiance: Vec3) {
+        let radius = self.filter.radius();
+        let x_lo = ((px - 0.5 - radius).ceil().max(0.0) as usize).max(self.x0);
+        let x_hi = ((px - 0.5 + radius).floor().max(0.0) as usize + 1).min(self.x1);
+        let y_lo = ((py - 0.5 - radius).ceil().max(0.0) as usize).max(self.y0);
+        let y_hi = ((py - 0.5 + radius).floor().max(0.0) as usize + 1).min(self.y1);
+        let width = self.x1 - self.x0;
+        for y in y_lo..y_hi {
+            for x in x_lo..x_hi {
+                let w = self.filter.eval(x as f32 + 0.5 - px, y as f32 + 0.5 - py);
+                let idx = (y - self.y0) * width + (x - self.x0);
+                self.sum[idx] += w * radiance;
+                self.weight[idx] += w;
+            }
+        }
+    }
+}
+
+/// The image being rendered, accumulating filtered samples.
+pub struct Film {
+    width: usize,
+    height: usize,
+    filter: Filter,
+    sum: Vec<Vec3>,
+    weight: Vec<f32>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            sum: vec![Vec3::default(); width * height],
+            weight: vec![0.0; width * height],
+        }
+    }
+
+    /// An empty tile for samples taken in pixels `x0..x1` by `y0..y1`.
+    pub fn tile(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> FilmTile {
+        let margin = self.filter.radius().ceil() as usize;
+        let x0 = x0.saturating_sub(margin);
+        let y0 = y0.saturating_sub(margin);
+        let x1 = (x1 + margin).min(self.width);
+        let y1 = (y1 + margin).min(self.height);
+        let len = (x1 - x0) * (y1 - y0);
+        FilmTile {
+            filter: self.filter,
+            x0,
+            y0,
+            x1,
+            y1,
+            sum: vec![Vec3::default(); len],
+            weight: vec![0.0; len],
+        }
+    }
+
+    /// Adds a finished tile into the image.
+    pub fn merge(&mut self, tile: FilmTile) {
+        let tile_width = tile.x1 - tile.x0;
+        for (idx, (sum, weight)) in tile.sum.into_iter().zip(tile.weight).enumerate() {
+            let x = tile.x0 + idx % tile_width;
+            let y = tile.y0 + idx / tile_width;
+            self.sum[y * self.width + x] += sum;
+            self.weight[y * self.width + x] += weight;
+        }
+    }
+
+    /// Every pixel's weighted average of the samples around it.
+    ///
+    /// With few samples, the negative lobes of a filter can cancel most of a
+    /// pixel's weight. Pixels left with hardly any weight stay black, and
+    /// negative averages are clamped to zero.
+    pub fn into_framebuffer(self) -> Framebuffer {
+        let pixels = self
+            .sum
+            .into_iter()
+            .zip(self.weight)
+            .map(|(sum, weight)| {
+                if MIN_WEIGHT < weight {
+                    let p = sum / weight;
+                    Vec3::new(p.x().max(0.0), p.y().max(0.0), p.z().max(0.0))
+                } else {
+                    Vec3::default()
+                }
+            })
+            .collect();
+        Framebuffer {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+#[test]
+fn test_filters_reproduce_flat_image() {
+    use super::rng::{random, reseed};
+
+    reseed(2);
+    for filter in [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell,
+        Filter::Lanczos,
+    ] {
+        assert!(0.0 < filter.eval(0.0, 0.0));
+        assert_eq!(filter.eval(filter.radius() + 0.1, 0.0), 0.0);
+        assert_eq!(filter.eval(-filter.radius(), 0.0), 0.0);
+
+        // Two tiles splitting an 8x4 image, each sampling only its half.
+        let mut film = Film::new(8, 4, filter);
+        for (x0, x1) in [(0, 4), (4, 8)] {
+            let mut tile = film.tile(x0, 0, x1, 4);
+            for y in 0..4 {
+                for x in x0..x1 {
+                    for _ in 0..16 {
+                        let px = x as f32 + random::<f32>();
+                        let py = y as f32 + random::<f32>();
+                        tile.add_sample(px, py, Vec3::new(0.25, 0.5, 1.0));
+                    }
+                }
+            }
+            film.merge(tile);
+        }
+        for p in film.into_framebuffer().pixels {
+            assert!((p.y() - 0.5).abs() < 1e-4, "{:?}: {:?}", filter, p);
+        }
+    }
+}
+
+#[test]
+fn test_box_border_and_lanczos_at_low_sample_counts() {
+    use super::rng::{random, reseed};
+
+    // A sample on the border between two pixels lands in one of them only.
+    assert_eq!(
+        Filter::Box.eval(0.5, 0.0) + Filter::Box.eval(-0.5, 0.0),
+        1.0
+    );
+    let mut film = Film::new(2, 1, Filter::Box);
+    let mut tile = film.tile(0, 0, 2, 1);
+    tile.add_sample(1.0, 0.5, Vec3::new(1.0, 1.0, 1.0));
+    film.merge(tile);
+    let pixels = film.into_framebuffer().pixels;
+    assert_eq!((pixels[0].x(), pixels[1].x()), (0.0, 1.0));
+
+    // A bright sample one and a half pixels away pulls pixel 4 below zero
+    // through a negative lobe.
+    let mut film = Film::new(8, 1, Filter::Lanczos);
+    let mut tile = film.tile(0, 0, 8, 1);
+    tile.add_sample(3.0, 0.5, Vec3::new(1.0, 1.0, 1.0));
+    tile.add_sample(5.0, 0.5, Vec3::default());
+    film.merge(tile);
+    assert_eq!(film.into_framebuffer().pixels[4].x(), 0.0);
+
+    // One sample per pixel of a random black and white image.
+    reseed(5);
+    let mut film = Film::new(32, 32, Filter::Lanczos);
+    let mut tile = film.tile(0, 0, 32, 32);
+    for y in 0..32 {
+        for x in 0..32 {
+            let radiance = if random::<f32>() < 0.5 { 0.0 } else { 1.0 };
+            let px = x as f32 + random::<f32>();
+            let py = y as f32 + random::<f32>();
+            tile.add_sample(px, py, Vec3::new(radiance, radiance, radiance));
+        }
+    }
+    film.merge(tile);
+    for p in film.into_framebuffer().pixels {
+        assert!(p.x().is_finite() && 0.0 <= p.x(), "{:?}", p);
+    }
+}
@@ -0,0 +1,290 @@
+use std::sync::Arc;
+
+use image::GrayImage;
+
+use super::aabb::AABB;
+use super::hitable::{HitRecord, Hitable};
+use super::material::Material;
+use super::perlin::Perlin;
+use super::ray::Ray;
+use super::triangle::hit_triangle;
+use super::vec3::*;
+
+/// One axis of the walk through the grid: which way the ray steps between
+/// cells, where it crosses into the next one and how far apart the
+/// crossings are.
+fn grid_axis(o: f32, d: f32, start: f32, size: f32, cell: usize) -> (isize, f32, f32) {
+    if 0.0 < d {
+        (1, (start + (cell + 1) as f32 * size - o) / d, size / d)
+    } else if d < 0.0 {
+        (-1, (start + cell as f32 * size - o) / d, -size / d)
+    } else {
+        (0, f32::INFINITY, f32::INFINITY)
+    }
+}
+
+/// Terrain over the rectangle from `p0` to `p1` in x and z, raised from
+/// `p0.y` to `p1.y` by a grid of samples between 0 and 1.
+///
+/// Each cell of the grid is split into two triangles, with normals
+/// interpolated from the slope at the samples. Rays walk the cells under
+/// them in order, so the cost grows with the grid's side rather than its
+/// area.
+pub struct Heightfield {
+    x0: f32,
+    z0: f32,
+    dx: f32,
+    dz: f32,
+    nx: usize,
+    nz: usize,
+    heights: Vec<f32>,
+    normals: Vec<Vec3>,
+    bbox: AABB,
+    material: Arc<dyn Material>,
+}
+
+impl Heightfield {
+    /// `samples` holds `nx` samples along x for each of `nz` rows along z.
+    pub fn new(
+        samples: &[f32],
+        nx: usize,
+        nz: usize,
+        p0: Vec3,
+        p1: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(2 <= nx && 2 <= nz, "a heightfield needs 2x2 samples");
+        assert_eq!(samples.len(), nx * nz);
+        let heights = samples
+            .iter()
+            .map(|h| p0.y() + h * (p1.y() - p0.y()))
+            .collect::<Vec<_>>();
+        let dx = (p1.x() - p0.x()) / (nx - 1) as f32;
+        let dz = (p1.z() - p0.z()) / (nz - 1) as f32;
+
+        // Central differences inside, one-sided along the edges.
+        let at = |i: usize, j: usize| heights[j * nx + i];
+        let mut normals = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+                let slope_x = (at(i1, j) - at(i0, j)) / ((i1 - i0) as f32 * dx);
+                let slope_z = (at(i, j1) - at(i, j0)) / ((j1 - j0) as f32 * dz);
+                normals.push(unit_vector(&Vec3::new(-slope_x, 1.0, -slope_z)));
+            }
+        }
+
+        let low = heights.iter().cloned().fold(f32::MAX, f32::min);
+        let high = heights.iter().cloned().fold(-f32::MAX, f32::max);
+        let bbox = AABB::new(
+            Vec3::new(p0.x(), low - 0.0001, p0.z()),
+            Vec3::new(p1.x(), high + 0.0001, p1.z()),
+        );
+        Self {
+            x0: p0.x(),
+            z0: p0.z(),
+            dx,
+            dz,
+            nx,
+            nz,
+            heights,
+            normals,
+            bbox,
+            material,
+        }
+    }
+
+    /// Heights from the brightness of a grayscale image, its rows running
+    /// along z.
+    pub fn from_image(img: &GrayImage, p0: Vec3, p1: Vec3, material: Arc<dyn Material>) -> Self {
+        let samples = img
+            .pixels()
+            .map(|pixel| pixel[0] as f32 / 255.0)
+            .collect::<Vec<_>>();
+        let (nx, nz) = img.dimensions();
+        Self::new(&samples, nx as usize, nz as usize, p0, p1, material)
+    }
+
+    /// Heights from `depth` octaves of turbulence, sampled `resolution`
+    /// times along each side at `scale` times the world coordinates, and
+    /// stretched to fill `p0.y` to `p1.y`.
+    pub fn from_noise(
+        noise: &Perlin,
+        scale: f32,
+        depth: u32,
+        resolution: usize,
+        p0: Vec3,
+        p1: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let step = (p1 - p0) / (resolution - 1) as f32;
+        let samples = (0..resolution * resolution)
+            .map(|idx| {
+                let x = p0.x() + (idx % resolution) as f32 * step.x();
+                let z = p0.z() + (idx / resolution) as f32 * step.z();
+                noise.turb(&(scale * Vec3::new(x, 0.0, z)), depth)
+            })
+            .collect::<Vec<_>>();
+        let low = samples.iter().cloned().fold(f32::MAX, f32::min);
+        let high = samples.iter().cloned().fold(-f32::MAX, f32::max);
+        let range = (high - low).max(1e-6);
+        let samples = samples
+            .iter()
+            .map(|h| (h - low) / range)
+            .collect::<Vec<_>>();
+        Self::new(&samples, resolution, resolution, p0, p1, material)
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Vec3 {
+        Vec3::new(
+            self.x0 + i as f32 * self.dx,
+            self.heights[j * self.nx + i],
+            self.z0 + j as f32 * self.dz,
+        )
+    }
+
+    /// The nearest hit on the two triangles of cell `(i, j)`.
+    fn hit_cell(&self, r: &Ray, i: usize, j: usize, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let (t, [a, b, c], u, v) = [[0, 1, 2], [0, 2, 3]]
+            .iter()
+            .filter_map(|&tri| {
+                let ids = tri.map(|k| corners[k]);
+                let (t, u, v) = hit_triangle(r, ids.map(|(i, j)| self.vertex(i, j)))?;
+                Some((t, ids, u, v))
+            })
+            .filter(|&(t, ..)| t_min < t && t < t_max)
+            .min_by(|x, y| x.0.total_cmp(&y.0))?;
+
+        let w = 1.0 - u - v;
+        let normal_at = |(i, j): (usize, usize)| self.normals[j * self.nx + i];
+        let p = r.point_at_parameter(t);
+        Some(HitRecord {
+            t,
+            p,
+            u: (p.x() - self.x0) / (self.dx * (self.nx - 1) as f32),
+            v: (p.z() - self.z0) / (self.dz * (self.nz - 1) as f32),
+            normal: unit_vector(&(w * normal_at(a) + u * normal_at(b) + v * normal_at(c))),
+            material: Arc::clone(&self.material),
+        })
+    }
+}
+
+impl Hitable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t_start, t_end) = self.bbox.hit(r, t_min, t_max)?;
+        let (o, d) = (r.origin(), r.direction());
+        let start = r.point_at_parameter(t_start);
+        let cell = |x: f32, x0: f32, size: f32, cells: usize| {
+            ((x - x0) / size).floor().clamp(0.0, (cells - 1) as f32) as usize
+        };
+        let mut i = cell(start.x(), self.x0, self.dx, self.nx - 1);
+        let mut j = cell(start.z(), self.z0, self.dz, self.nz - 1);
+        let (step_x, mut next_x, delta_x) = grid_axis(o.x(), d.x(), self.x0, self.dx, i);
+        let (step_z, mut next_z, delta_z) = grid_axis(o.z(), d.z(), self.z0, self.dz, j);
+
+        let mut t = t_start;
+        loop {
+            let t_exit = next_x.min(next_z).min(t_end);
+            // Cells the ray passes over entirely are skipped.
+            let low = (o.y() + t * d.y()).min(o.y() + t_exit * d.y());
+            let high = [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)]
+                .iter()
+                .map(|&(i, j)| self.heights[j * self.nx + i])
+                .fold(-f32::MAX, f32::max);
+            if low <= high {
+                if let Some(rec) = self.hit_cell(r, i, j, t_min, t_max) {
+                    return Some(rec);
+                }
+            }
+            if t_end <= t_exit {
+                return None;
+            }
+            if next_x < next_z {
+                i = i.checked_add_signed(step_x).filter(|&i| i < self.nx - 1)?;
+                next_x += delta_x;
+            } else {
+                j = j.checked_add_signed(step_z).filter(|&j| j < self.nz - 1)?;
+                next_z += delta_z;
+            }
+            t = t_exit;
+        }
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        self.bbox.clone()
+    }
+}
+
+#[test]
+fn test_heightfield() {
+    use super::material::Lambertian;
+    use super::rng::reseed;
+    use super::texture::ConstantTexture;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.5, 0.5, 0.5),
+    ))));
+
+    // A ramp rising along x, from an image dark on the left and bright on
+    // the right.
+    let ramp = GrayImage::from_fn(2, 3, |x, _| image::Luma([(x * 255) as u8]));
+    let ramp = Heightfield::from_image(
+        &ramp,
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(4.0, 4.0, 2.0),
+        Arc::clone(&gray),
+    );
+    let r = Ray::new(Vec3::new(2.5, 10.0, 0.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = ramp.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 7.5).abs() < 1e-4, "{}", rec.t);
+    assert!((rec.u - 0.625).abs() < 1e-5 && (rec.v - 0.25).abs() < 1e-5);
+    let slope = Vec3::new(-1.0, 1.0, 0.0) / 2f32.sqrt();
+    assert!((dot(&rec.normal, &slope) - 1.0).abs() < 1e-4);
+    // Skimming along the foot of the ramp, then running into it.
+    let r = Ray::new(Vec3::new(-1.0, 1.5, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    assert!((ramp.hit(&r, 0.001, f32::MAX).unwrap().t - 2.5).abs() < 1e-4);
+
+    // Walking the grid finds the same hits as testing every cell.
+    reseed(7);
+    let terrain = Heightfield::from_noise(
+        &Perlin::new(),
+        0.5,
+        4,
+        33,
+        Vec3::new(-4.0, 0.0, -4.0),
+        Vec3::new(4.0, 2.0, 4.0),
+        gray,
+    );
+    let mut rng = StdRng::seed_from_u64(8);
+    let mut hits = 0;
+    for _ in 0..300 {
+        let o = Vec3::new(
+            rng.gen_range(-6.0..6.0),
+            rng.gen_range(0.0..5.0),
+            rng.gen_range(-6.0..6.0),
+        );
+        let target = Vec3::new(
+            rng.gen_range(-4.0..4.0),
+            rng.gen_range(0.0..2.0),
+            rng.gen_range(-4.0..4.0),
+        );
+        let r = Ray::new(o, target - o, 0.0);
+        let brute = (0..32 * 32)
+            .filter_map(|idx| terrain.hit_cell(&r, idx % 32, idx / 32, 0.001, f32::MAX))
+            .min_by(|a, b| a.t.total_cmp(&b.t));
+        match (terrain.hit(&r, 0.001, f32::MAX), brute) {
+            (Some(a), Some(b)) => {
+                assert!((a.t - b.t).abs() < 1e-4, "{} vs {}", a.t, b.t);
+                assert!(0.0 < a.normal.y());
+                hits += 1;
+            }
+            (None, None) => {}
+            (a, b) => panic!("{:?} vs {:?}", a.map(|a| a.t), b.map(|b| b.t)),
+        }
+    }
+    assert!(100 < hits);
+}
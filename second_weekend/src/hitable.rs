@@ -1,9 +1,10 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use super::ray::Ray;
-use super::vec3::Vec3;
-use super::material::Material;
 use super::aabb::AABB;
+use super::material::Material;
+use super::ray::Ray;
+use super::sampler::Sampler;
+use super::vec3::{dot, Vec3};
 
 pub struct HitRecord {
     pub t: f32,
@@ -11,10 +12,112 @@ pub struct HitRecord {
     pub u: f32,
     pub v: f32,
     pub normal: Vec3,
-    pub material: Rc<dyn Material>
+    pub material: Arc<dyn Material>,
+}
+
+/// Where a ray's line crosses the surface of a solid, going in or out.
+pub struct Crossing {
+    pub hit: HitRecord,
+    pub entering: bool,
 }
 
-pub trait Hitable {
+pub trait Hitable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
     fn bounding_box(&self, t0: f32, t1: f32) -> AABB;
+
+    /// Same as `hit`, but whatever random numbers decide where the ray stops,
+    /// such as a medium's free-flight distance, are drawn from `sampler`.
+    /// Objects holding others pass the sampler on to them.
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.hit(r, t_min, t_max)
+    }
+
+    /// Whether the object holds emitters that `random` can sample.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// Density, with respect to solid angle, of `random` returning the
+    /// direction `v` from `o`.
+    fn pdf_value(&self, _o: &Vec3, _v: &Vec3) -> f32 {
+        0.0
+    }
+
+    /// Random direction from `o` towards a point on the surface, for
+    /// sampling lights directly.
+    fn random(&self, _o: &Vec3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Whether the object bounds a solid, so that `crossings` tells inside
+    /// from outside and it can take part in constructive solid geometry.
+    fn is_solid(&self) -> bool {
+        false
+    }
+
+    /// Every crossing of the surface along the whole line through `r`,
+    /// nearest first, for combining solids. The line starts out inside when
+    /// the first crossing leaves. Only meaningful when `is_solid`.
+    ///
+    /// By default this steps from one `hit` to the next, telling entries
+    /// from exits by which way the normal faces. Walls thinner than the step
+    /// are lost, so solids with exact crossings override it.
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        const STEP: f32 = 1e-4;
+        let mut crossings = Vec::new();
+        let mut t_min = -f32::MAX;
+        while let Some(hit) = self.hit(r, t_min, f32::MAX) {
+            // Far out, a step this small can round away to nothing.
+            t_min = (hit.t + STEP).max(hit.t + hit.t.abs() * f32::EPSILON);
+            let entering = dot(&hit.normal, r.direction()) < 0.0;
+            crossings.push(Crossing { hit, entering });
+        }
+        crossings
+    }
+}
+
+impl<T: Hitable + ?Sized> Hitable for Box<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
+        (**self).bounding_box(t0, t1)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        (**self).hit_sampled(r, t_min, t_max, sampler)
+    }
+
+    fn is_light(&self) -> bool {
+        (**self).is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        (**self).pdf_value(o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        (**self).random(o, sampler)
+    }
+
+    fn is_solid(&self) -> bool {
+        (**self).is_solid()
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        (**self).crossings(r)
+    }
 }
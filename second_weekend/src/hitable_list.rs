@@ -1,85 +1,470 @@
 use std::iter::FromIterator;
-use std::rc::Rc;
 
 use ordered_float::OrderedFloat;
-use rand::random;
 
 use super::aabb::{surrounding_box, AABB};
 use super::hitable::{HitRecord, Hitable};
 use super::ray::Ray;
+use super::sampler::{Independent, Sampler};
+use super::vec3::Vec3;
 
-struct BVHNode {
-    left: Rc<dyn Hitable>,
-    right: Rc<dyn Hitable>,
+const SAH_BINS: usize = 12;
+
+/// How a BVH node divides its objects between its two children.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SplitStrategy {
+    /// Binned surface area heuristic over all three axes.
+    #[default]
+    Sah,
+    /// Halves the objects along the longest axis of their centroids.
+    Median,
+}
+
+/// An object waiting to be placed in the hierarchy, by its index and
+/// bounding box.
+struct Primitive {
+    index: usize,
     box_: AABB,
 }
 
-impl BVHNode {
-    pub fn new(hittables: &mut [Rc<dyn Hitable>], time0: f32, time1: f32) -> Self {
-        let axis = (3.0 * random::<f32>()).floor() as usize;
-        hittables.sort_by_key(|hitable| OrderedFloat(hitable.bounding_box(time0, time1).min[axis]));
-
-        let (left, right) = match hittables.len() {
-            1 => (Rc::clone(&hittables[0]), Rc::clone(&hittables[0])),
-            2 => (Rc::clone(&hittables[0]), Rc::clone(&hittables[1])),
-            _ => {
-                let mid = hittables.len() / 2;
-                (
-                    Rc::new(BVHNode::new(&mut hittables[0..mid], time0, time1)) as Rc<dyn Hitable>,
-                    Rc::new(BVHNode::new(&mut hittables[mid..], time0, time1)) as Rc<dyn Hitable>,
-                )
+fn centroid(b: &AABB, axis: usize) -> f32 {
+    0.5 * (b.min[axis] + b.max[axis])
+}
+
+fn centroid_bounds(prims: &[Primitive]) -> AABB {
+    prims
+        .iter()
+        .map(|prim| {
+            let c = 0.5 * (prim.box_.min + prim.box_.max);
+            AABB::new(c, c)
+        })
+        .reduce(|acc, b| surrounding_box(&acc, &b))
+        .expect("cannot bound an empty set of objects")
+}
+
+fn longest_axis(b: &AABB) -> usize {
+    let extent = b.max - b.min;
+    (0..3)
+        .max_by_key(|&axis| OrderedFloat(extent[axis]))
+        .unwrap()
+}
+
+type Bucket = (usize, Option<AABB>);
+
+/// Groups `prims` by centroid along `axis`, one bucket per object when there
+/// are few of them and `SAH_BINS` equally wide bins otherwise.
+fn buckets(prims: &[Primitive], bounds: &AABB, axis: usize) -> Vec<Bucket> {
+    if prims.len() <= SAH_BINS {
+        let mut sorted = prims.iter().map(|prim| &prim.box_).collect::<Vec<_>>();
+        sorted.sort_by_key(|b| OrderedFloat(centroid(b, axis)));
+        return sorted.into_iter().map(|b| (1, Some(b.clone()))).collect();
+    }
+
+    let lo = bounds.min[axis];
+    let extent = bounds.max[axis] - lo;
+    let mut bins: Vec<Bucket> = vec![(0, None); SAH_BINS];
+    for prim in prims {
+        let bin = ((centroid(&prim.box_, axis) - lo) / extent * SAH_BINS as f32) as usize;
+        let (count, bin_box) = &mut bins[bin.min(SAH_BINS - 1)];
+        *count += 1;
+        *bin_box = merge(bin_box.take(), &Some(prim.box_.clone()));
+    }
+    bins
+}
+
+/// Cheapest split between consecutive buckets as its cost and the number of
+/// objects on the left.
+fn cheapest_split(buckets: &[Bucket]) -> Option<(f32, usize)> {
+    // Sweep from the right first so each split can combine both sides.
+    let mut right = vec![(0, 0.0); buckets.len()];
+    let mut count = 0;
+    let mut acc: Option<AABB> = None;
+    for i in (1..buckets.len()).rev() {
+        count += buckets[i].0;
+        acc = merge(acc, &buckets[i].1);
+        right[i] = (count, acc.as_ref().map_or(0.0, AABB::surface_area));
+    }
+
+    let mut best: Option<(f32, usize)> = None;
+    let mut count = 0;
+    let mut acc: Option<AABB> = None;
+    for split in 1..buckets.len() {
+        count += buckets[split - 1].0;
+        acc = merge(acc, &buckets[split - 1].1);
+        let (right_count, right_area) = right[split];
+        if count == 0 || right_count == 0 {
+            continue;
+        }
+        let left_area = acc.as_ref().map_or(0.0, AABB::surface_area);
+        let cost = count as f32 * left_area + right_count as f32 * right_area;
+        if cost < best.map_or(f32::INFINITY, |(best_cost, _)| best_cost) {
+            best = Some((cost, count));
+        }
+    }
+    best
+}
+
+/// Picks the axis and number of leading objects, once sorted along that
+/// axis, that minimise the summed surface area times object count of the
+/// two children.
+fn sah_split(prims: &[Primitive], bounds: &AABB) -> Option<(usize, usize)> {
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        if bounds.max[axis] <= bounds.min[axis] {
+            continue;
+        }
+        if let Some((cost, count)) = cheapest_split(&buckets(prims, bounds, axis)) {
+            if cost < best.map_or(f32::INFINITY, |(best_cost, _, _)| best_cost) {
+                best = Some((cost, axis, count));
             }
-        };
-        let box_ = surrounding_box(
-            &left.bounding_box(time0, time1),
-            &right.bounding_box(time0, time1),
-        );
-        Self { left, right, box_ }
+        }
     }
+    best.map(|(_, axis, count)| (axis, count))
 }
 
-impl Hitable for BVHNode {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        self.box_.hit(&r, t_min, t_max).and_then(|()| {
-            let left_hit = self.left.hit(&r, t_min, t_max);
-            let right_hit = self.right.hit(&r, t_min, t_max);
-            match (left_hit, right_hit) {
-                (Some(lh), Some(rh)) => {
-                    if lh.t < rh.t {
-                        Some(lh)
-                    } else {
-                        Some(rh)
+fn merge(acc: Option<AABB>, b: &Option<AABB>) -> Option<AABB> {
+    match (acc, b) {
+        (Some(acc), Some(b)) => Some(surrounding_box(&acc, b)),
+        (None, b) => b.clone(),
+        (acc, None) => acc,
+    }
+}
+
+enum NodeKind {
+    Leaf {
+        index: usize,
+    },
+    /// The first child directly follows its parent, `second` is the index of
+    /// the other one. `axis` is the axis the objects were split along.
+    Interior {
+        second: usize,
+        axis: usize,
+    },
+}
+
+struct LinearNode {
+    box_: AABB,
+    kind: NodeKind,
+}
+
+/// Splits `prims` recursively, appending the nodes depth first, and returns
+/// the bounding box of the subtree.
+fn flatten(nodes: &mut Vec<LinearNode>, prims: &mut [Primitive], strategy: SplitStrategy) -> AABB {
+    if prims.len() == 1 {
+        let box_ = prims[0].box_.clone();
+        nodes.push(LinearNode {
+            box_: box_.clone(),
+            kind: NodeKind::Leaf {
+                index: prims[0].index,
+            },
+        });
+        return box_;
+    }
+
+    let bounds = centroid_bounds(prims);
+    let split = match strategy {
+        SplitStrategy::Sah => sah_split(prims, &bounds),
+        SplitStrategy::Median => None,
+    };
+    // The median split also covers objects that share one centroid.
+    let (axis, mid) = split.unwrap_or((longest_axis(&bounds), prims.len() / 2));
+    prims.sort_by_key(|prim| OrderedFloat(centroid(&prim.box_, axis)));
+
+    let idx = nodes.len();
+    nodes.push(LinearNode {
+        box_: bounds,
+        kind: NodeKind::Interior { second: 0, axis },
+    });
+    let (left, right) = prims.split_at_mut(mid);
+    let left_box = flatten(nodes, left, strategy);
+    let second = nodes.len();
+    let right_box = flatten(nodes, right, strategy);
+
+    let box_ = surrounding_box(&left_box, &right_box);
+    nodes[idx] = LinearNode {
+        box_: box_.clone(),
+        kind: NodeKind::Interior { second, axis },
+    };
+    box_
+}
+
+/// Bounding volume hierarchy over a set of boxes, laid out as a flat array
+/// whose leaves refer to the boxes by index.
+///
+/// Unbounded boxes stay out of the hierarchy and are tested on every ray.
+pub struct Bvh {
+    nodes: Vec<LinearNode>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    /// Panics if there are no boxes.
+    pub fn new(boxes: &[AABB], strategy: SplitStrategy) -> Self {
+        assert!(!boxes.is_empty(), "cannot bound an empty set of objects");
+        let (mut prims, unbounded): (Vec<_>, Vec<_>) = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, box_)| Primitive {
+                index,
+                box_: box_.clone(),
+            })
+            .partition(|prim| prim.box_.is_bounded());
+        let mut nodes = Vec::with_capacity(2 * prims.len());
+        if !prims.is_empty() {
+            flatten(&mut nodes, &mut prims, strategy);
+        }
+        Self {
+            nodes,
+            unbounded: unbounded.into_iter().map(|prim| prim.index).collect(),
+        }
+    }
+
+    /// Fits the hierarchy around new `boxes`, one for each box it was built
+    /// over, keeping its shape.
+    ///
+    /// This is much cheaper than building it anew, but traversal slows down
+    /// the further the boxes move from where they were. Panics if a box
+    /// turns from bounded to unbounded or back.
+    #[cfg(test)]
+    pub fn refit(&mut self, boxes: &[AABB]) {
+        for &index in &self.unbounded {
+            assert!(!boxes[index].is_bounded(), "cannot refit a bounded box");
+        }
+        // Children always come after their parent.
+        for current in (0..self.nodes.len()).rev() {
+            let box_ = match self.nodes[current].kind {
+                NodeKind::Leaf { index } => {
+                    assert!(boxes[index].is_bounded(), "cannot refit an unbounded box");
+                    boxes[index].clone()
+                }
+                NodeKind::Interior { second, .. } => {
+                    surrounding_box(&self.nodes[current + 1].box_, &self.nodes[second].box_)
+                }
+            };
+            self.nodes[current].box_ = box_;
+        }
+    }
+
+    pub fn bounding_box(&self) -> AABB {
+        if self.unbounded.is_empty() {
+            self.nodes[0].box_.clone()
+        } else {
+            AABB::infinite()
+        }
+    }
+
+    /// Closest hit among the leaves, where `hit_leaf(index, t_max)` hits the
+    /// object of the box at `index`.
+    ///
+    /// Visits the child on the ray's side of the split first and only keeps
+    /// looking for hits closer than the best one so far.
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut hit_leaf: impl FnMut(usize, f32) -> Option<HitRecord>,
+    ) -> Option<HitRecord> {
+        let dir_is_neg = [
+            r.direction().x() < 0.0,
+            r.direction().y() < 0.0,
+            r.direction().z() < 0.0,
+        ];
+        let mut closest = None;
+        let mut closest_t = t_max;
+        for &index in &self.unbounded {
+            if let Some(rec) = hit_leaf(index, closest_t) {
+                closest_t = rec.t;
+                closest = Some(rec);
+            }
+        }
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let mut to_visit = Vec::with_capacity(64);
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.box_.hit(r, t_min, closest_t).is_some() {
+                match node.kind {
+                    NodeKind::Leaf { index } => {
+                        if let Some(rec) = hit_leaf(index, closest_t) {
+                            closest_t = rec.t;
+                            closest = Some(rec);
+                        }
+                    }
+                    NodeKind::Interior { second, axis } => {
+                        let (near, far) = if dir_is_neg[axis] {
+                            (second, current + 1)
+                        } else {
+                            (current + 1, second)
+                        };
+                        to_visit.push(far);
+                        current = near;
+                        continue;
                     }
                 }
-                (Some(lh), None) => Some(lh),
-                (None, Some(rh)) => Some(rh),
-                _ => None,
             }
-        })
+            match to_visit.pop() {
+                Some(next) => current = next,
+                None => return closest,
+            }
+        }
     }
-    fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
-        surrounding_box(
-            &self.left.bounding_box(t0, t1),
-            &self.right.bounding_box(t0, t1),
+}
+
+/// Which objects of a collection hold lights, by their index.
+///
+/// Directions towards the lights pick one of them uniformly, so the density
+/// is their average.
+pub struct LightList(Vec<usize>);
+
+impl LightList {
+    pub fn new(objects: &[impl Hitable]) -> Self {
+        Self(
+            (0..objects.len())
+                .filter(|&idx| objects[idx].is_light())
+                .collect(),
         )
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `objects` must be the ones the list was made from.
+    pub fn pdf_value(&self, objects: &[impl Hitable], o: &Vec3, v: &Vec3) -> f32 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        let sum = self
+            .0
+            .iter()
+            .map(|&idx| objects[idx].pdf_value(o, v))
+            .sum::<f32>();
+        sum / self.0.len() as f32
+    }
+
+    /// `objects` must be the ones the list was made from.
+    pub fn random(&self, objects: &[impl Hitable], o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        if self.0.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let idx = (sampler.next_1d() * self.0.len() as f32) as usize;
+        objects[self.0[idx.min(self.0.len() - 1)]].random(o, sampler)
+    }
+}
+
+/// Objects kept in a bounding volume hierarchy.
+///
+/// The list also keeps track of which objects hold lights, and samples
+/// among those when asked for a direction towards its emitters.
+pub struct HitableList {
+    bvh: Bvh,
+    objects: Vec<Box<dyn Hitable>>,
+    lights: LightList,
+}
+
+impl HitableList {
+    pub fn new(objects: Vec<Box<dyn Hitable>>, strategy: SplitStrategy) -> Self {
+        let boxes = objects
+            .iter()
+            .map(|object| object.bounding_box(0.0, 1.0))
+            .collect::<Vec<_>>();
+        let bvh = Bvh::new(&boxes, strategy);
+        let lights = LightList::new(&objects);
+        HitableList {
+            bvh,
+            objects,
+            lights,
+        }
+    }
 }
 
-pub struct HitableList(BVHNode);
 impl Hitable for HitableList {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        self.0.hit(&r, t_min, t_max)
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.bvh.hit(r, t_min, t_max, |idx, t_max| {
+            self.objects[idx].hit_sampled(r, t_min, t_max, sampler)
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        self.bvh.bounding_box()
+    }
+
+    fn is_light(&self) -> bool {
+        !self.lights.is_empty()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.lights.pdf_value(&self.objects, o, v)
     }
 
-    fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
-        self.0.bounding_box(t0, t1)
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.lights.random(&self.objects, o, sampler)
     }
 }
 
 impl FromIterator<Box<dyn Hitable>> for HitableList {
     fn from_iter<I: IntoIterator<Item = Box<dyn Hitable>>>(iter: I) -> HitableList {
-        let mut inner = iter.into_iter().map(Rc::from).collect::<Vec<_>>();
-        let root = BVHNode::new(&mut inner, 0.0, 1.0);
-        HitableList(root)
+        HitableList::new(iter.into_iter().collect(), SplitStrategy::default())
+    }
+}
+
+#[test]
+fn test_split_strategies_find_closest_hit() {
+    use std::sync::Arc;
+
+    use super::material::{Dielectric, Material};
+    use super::sphere::Sphere;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let material: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let spheres = (0..200)
+        .map(|_| {
+            let center = Vec3::new(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            (center, rng.gen_range(0.1..1.0))
+        })
+        .collect::<Vec<_>>();
+    let objects = || {
+        spheres
+            .iter()
+            .map(|&(center, radius)| {
+                Box::new(Sphere::new(center, radius, Arc::clone(&material))) as Box<dyn Hitable>
+            })
+            .collect::<Vec<_>>()
+    };
+    let sah = HitableList::new(objects(), SplitStrategy::Sah);
+    let median = HitableList::new(objects(), SplitStrategy::Median);
+
+    for _ in 0..500 {
+        let origin = Vec3::new(rng.gen_range(-12.0..12.0), rng.gen_range(-12.0..12.0), 5.0);
+        let target = Vec3::new(rng.gen_range(-12.0..12.0), rng.gen_range(-12.0..12.0), 0.0);
+        let r = Ray::new(origin, target - origin, 0.0);
+        let expected = objects()
+            .iter()
+            .filter_map(|s| s.hit(&r, 0.001, f32::MAX))
+            .map(|rec| rec.t)
+            .min_by_key(|&t| OrderedFloat(t));
+        for bvh in [&sah, &median] {
+            assert_eq!(bvh.hit(&r, 0.001, f32::MAX).map(|rec| rec.t), expected);
+        }
     }
 }
@@ -1,27 +1,12 @@
-use std::{collections::btree_set::Difference, f64::consts::PI};
-
-use rand::random;
+use std::f32::consts::PI;
 
 use super::hitable::HitRecord;
+use super::pdf::{CosinePdf, FuzzPdf, Pdf};
 use super::ray::Ray;
+use super::sampler::Sampler;
 use super::texture::Texture;
 use super::vec3::*;
 
-fn random_in_unit_sphere() -> Vec3 {
-    let r = random::<f32>();
-    let theta = 2.0 * PI * random::<f64>();
-    let phi = 2.0 * PI * random::<f64>();
-    polar_to_cartesian(r, theta as f32, phi as f32)
-}
-
-fn polar_to_cartesian(r: f32, theta: f32, phi: f32) -> Vec3 {
-    Vec3::new(
-        r * phi.sin() * theta.cos(),
-        r * phi.sin() * theta.sin(),
-        r * phi.cos(),
-    )
-}
-
 fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
     *v - 2.0 * dot(v, n) * *n
 }
@@ -45,11 +30,35 @@ fn schlick(cosine: f32, ref_idx: f32) -> f32 {
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
 
-pub trait Material {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Vec3, Ray)>;
-    fn emitted(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+pub enum ScatterRecord {
+    /// The material picked the outgoing ray itself, as mirrors and glass do.
+    Specular { attenuation: Vec3, ray: Ray },
+    /// Outgoing directions may be drawn from `pdf` or any other density, and
+    /// are weighted by `Material::scattering_pdf`.
+    Lobe {
+        attenuation: Vec3,
+        pdf: Box<dyn Pdf>,
+    },
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
+    /// Density of scattering into `scattered`, for materials returning a
+    /// `ScatterRecord::Lobe`. It may differ from the lobe's own `pdf`.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        0.0
+    }
+    fn emitted(&self, _u: f32, _v: f32, _p: &Vec3) -> Vec3 {
         Vec3::default()
     }
+    fn is_emissive(&self) -> bool {
+        false
+    }
 }
 
 pub struct Lambertian {
@@ -63,15 +72,26 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Vec3, Ray)> {
-        let target = rec.p + rec.normal + random_in_unit_sphere();
-        Some((
-            self.albedo.value(rec.u, rec.v, &rec.p),
-            Ray::new(rec.p, target - rec.p, r_in.time),
-        ))
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Lobe {
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf: Box::new(CosinePdf::new(&rec.normal)),
+        })
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        let cosine = dot(&rec.normal, &unit_vector(scattered.direction()));
+        (cosine / PI).max(0.0)
     }
 }
 
+/// A mirror when `fuzz` is zero, otherwise reflecting towards random points
+/// within a ball of radius `fuzz` around the tip of the mirror direction.
 pub struct Metal {
     albedo: Vec3,
     fuzz: f32,
@@ -87,18 +107,36 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Vec3, Ray)> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let reflected = reflect(&unit_vector(r_in.direction()), &rec.normal);
-        let scattered = Ray::new(
-            rec.p,
-            reflected + self.fuzz * random_in_unit_sphere(),
-            r_in.time,
-        );
-        if 0.0 < dot(&reflected, &rec.normal) {
-            Some((self.albedo, scattered))
-        } else {
-            None
+        if dot(&reflected, &rec.normal) <= 0.0 {
+            return None;
+        }
+        if self.fuzz <= 0.0 {
+            return Some(ScatterRecord::Specular {
+                attenuation: self.albedo,
+                ray: Ray::new(rec.p, reflected, r_in.time),
+            });
         }
+        Some(ScatterRecord::Lobe {
+            attenuation: self.albedo,
+            pdf: Box::new(FuzzPdf::new(&reflected, self.fuzz)),
+        })
+    }
+
+    /// The reflection perturbed within a ball of `fuzz`, cut off below the
+    /// surface.
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        if self.fuzz <= 0.0 || dot(scattered.direction(), &rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        let reflected = reflect(&unit_vector(r_in.direction()), &rec.normal);
+        FuzzPdf::new(&reflected, self.fuzz).value(scattered.direction())
     }
 }
 
@@ -113,7 +151,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Vec3, Ray)> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let (outward_normal, ni_over_nt, cosine_coeff) = if 0.0 < dot(r_in.direction(), &rec.normal)
         {
             (-rec.normal, self.ref_idx, self.ref_idx)
@@ -126,7 +169,7 @@ impl Material for Dielectric {
                 let cosine =
                     cosine_coeff * dot(r_in.direction(), &rec.normal) / r_in.direction().lenght();
                 let refract_prob = schlick(cosine, self.ref_idx);
-                if refract_prob < random::<f32>() {
+                if refract_prob < sampler.next_1d() {
                     Some(refracted)
                 } else {
                     None
@@ -135,7 +178,10 @@ impl Material for Dielectric {
             .unwrap_or_else(|| reflect(r_in.direction(), &rec.normal));
         let attenuation = Vec3::new(1.0, 1.0, 1.0);
         let scattered = Ray::new(rec.p, scatter_direction, r_in.time);
-        Some((attenuation, scattered))
+        Some(ScatterRecord::Specular {
+            attenuation,
+            ray: scattered,
+        })
     }
 }
 
@@ -150,11 +196,20 @@ impl DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Vec3, Ray)> {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         None
     }
 
     fn emitted(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
         self.emit.value(u, v, p)
     }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
 }
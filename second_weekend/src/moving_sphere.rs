@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::aabb::{surrounding_box, AABB};
 use super::hitable::{HitRecord, Hitable};
@@ -12,7 +12,7 @@ pub struct MovingSphere {
     time0: f32,
     time1: f32,
     radius: f32,
-    material: Rc<dyn Material>,
+    material: Arc<dyn Material>,
 }
 
 impl MovingSphere {
@@ -22,7 +22,7 @@ impl MovingSphere {
         time0: f32,
         time1: f32,
         radius: f32,
-        material: Rc<dyn Material>,
+        material: Arc<dyn Material>,
     ) -> Self {
         Self {
             center0,
@@ -59,7 +59,7 @@ impl Hitable for MovingSphere {
                     u: 0.0,
                     v: 0.0,
                     normal,
-                    material: Rc::clone(&self.material),
+                    material: Arc::clone(&self.material),
                 });
             }
 
@@ -74,7 +74,7 @@ impl Hitable for MovingSphere {
                     u: 0.0,
                     v: 0.0,
                     normal,
-                    material: Rc::clone(&self.material),
+                    material: Arc::clone(&self.material),
                 });
             }
         }
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+use image::RgbImage;
+
+use super::hitable::Hitable;
+use super::hitable_list::HitableList;
+use super::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use super::texture::{ConstantTexture, ImageTexture, Texture};
+use super::triangle::TriangleMesh;
+use super::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Load(PathBuf, tobj::LoadError),
+    Texture(PathBuf, String),
+    Empty(PathBuf),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Load(path, err) => write!(f, "cannot load `{}`: {}", path.display(), err),
+            ObjError::Texture(path, err) => {
+                write!(f, "cannot load texture `{}`: {}", path.display(), err)
+            }
+            ObjError::Empty(path) => write!(f, "`{}` has no faces", path.display()),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+fn vec3(e: [f32; 3]) -> Vec3 {
+    Vec3::new(e[0], e[1], e[2])
+}
+
+fn parse_color(value: &str) -> Option<Vec3> {
+    let e = value
+        .split_whitespace()
+        .map(|c| c.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match e[..] {
+        [e0, e1, e2] => Some(Vec3::new(e0, e1, e2)),
+        _ => None,
+    }
+}
+
+struct MaterialConverter<'a> {
+    base_dir: &'a Path,
+    images: HashMap<PathBuf, RgbImage>,
+}
+
+impl<'a> MaterialConverter<'a> {
+    fn diffuse(&mut self, mtl: &tobj::Material) -> Result<Box<dyn Texture>, ObjError> {
+        if mtl.diffuse_texture.is_empty() {
+            return Ok(Box::new(ConstantTexture::new(vec3(mtl.diffuse))));
+        }
+
+        let path = self.base_dir.join(&mtl.diffuse_texture);
+        if !self.images.contains_key(&path) {
+            let img = ImageReader::open(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|reader| reader.decode().map_err(|err| err.to_string()))
+                .map_err(|err| ObjError::Texture(path.clone(), err))?;
+            self.images.insert(path.clone(), img.to_rgb8());
+        }
+        Ok(Box::new(ImageTexture::new(self.images[&path].clone())))
+    }
+
+    /// Maps an MTL description onto the closest of our materials.
+    ///
+    /// Emissive materials (`Ke`) become lights, transparent ones (`d < 1` or
+    /// a refractive `illum`) dielectrics, reflective ones (`illum 3` or `5`)
+    /// metals whose fuzz follows the Phong exponent `Ns`, and everything else
+    /// is Lambertian with `Kd` or `map_Kd` as albedo.
+    fn convert(&mut self, mtl: &tobj::Material) -> Result<Arc<dyn Material>, ObjError> {
+        let emission = mtl
+            .unknown_param
+            .get("Ke")
+            .and_then(|value| parse_color(value))
+            .filter(|ke| 0.0 < ke.x() + ke.y() + ke.z());
+        if let Some(ke) = emission {
+            return Ok(Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+                ke,
+            )))));
+        }
+
+        let material: Arc<dyn Material> = match mtl.illumination_model {
+            Some(4) | Some(6) | Some(7) | Some(9) => Arc::new(Dielectric::new(mtl.optical_density)),
+            _ if mtl.dissolve < 1.0 => Arc::new(Dielectric::new(mtl.optical_density)),
+            Some(3) | Some(5) => Arc::new(Metal::new(
+                vec3(mtl.specular),
+                (2.0 / (mtl.shininess + 2.0)).sqrt(),
+            )),
+            _ => Arc::new(Lambertian::new(self.diffuse(mtl)?)),
+        };
+        Ok(material)
+    }
+}
+
+/// Loads a Wavefront OBJ file, one triangle mesh per group and material.
+///
+/// Polygons are triangulated and materials come from the referenced MTL
+/// files unless `material` overrides them all.
+pub fn load(path: &Path, material: Option<Arc<dyn Material>>) -> Result<HitableList, ObjError> {
+    let options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ignore_points: true,
+        ignore_lines: true,
+    };
+    let (models, mtls) =
+        tobj::load_obj(path, &options).map_err(|err| ObjError::Load(path.to_path_buf(), err))?;
+
+    // With an override every mesh falls back to `default_material`.
+    let (materials, default_material) = match material {
+        Some(material) => (vec![], material),
+        None => {
+            let mtls = mtls.map_err(|err| ObjError::Load(path.to_path_buf(), err))?;
+            let mut converter = MaterialConverter {
+                base_dir: path.parent().unwrap_or_else(|| Path::new("")),
+                images: HashMap::new(),
+            };
+            let materials = mtls
+                .iter()
+                .map(|mtl| converter.convert(mtl))
+                .collect::<Result<Vec<_>, _>>()?;
+            let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(
+                ConstantTexture::new(Vec3::new(0.73, 0.73, 0.73)),
+            )));
+            (materials, gray)
+        }
+    };
+
+    let meshes = models
+        .into_iter()
+        .filter(|model| !model.mesh.indices.is_empty())
+        .map(|model| {
+            let mesh = model.mesh;
+            let vertex_count = mesh.positions.len() / 3;
+            let positions = mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| Vec3::new(p[0], p[1], p[2]))
+                .collect();
+            let normals = Some(&mesh.normals)
+                .filter(|normals| normals.len() == 3 * vertex_count)
+                .map(|normals| {
+                    normals
+                        .chunks_exact(3)
+                        .map(|n| Vec3::new(n[0], n[1], n[2]))
+                        .collect()
+                });
+            let texcoords = Some(&mesh.texcoords)
+                .filter(|texcoords| texcoords.len() == 2 * vertex_count)
+                .map(|texcoords| texcoords.chunks_exact(2).map(|t| [t[0], t[1]]).collect());
+            let indices = mesh
+                .indices
+                .chunks_exact(3)
+                .map(|i| [i[0] as usize, i[1] as usize, i[2] as usize])
+                .collect();
+            let material = mesh
+                .material_id
+                .and_then(|id| materials.get(id))
+                .unwrap_or(&default_material);
+            Box::new(TriangleMesh::new(
+                positions,
+                normals,
+                texcoords,
+                indices,
+                Arc::clone(material),
+            )) as Box<dyn Hitable>
+        })
+        .collect::<Vec<_>>();
+
+    if meshes.is_empty() {
+        return Err(ObjError::Empty(path.to_path_buf()));
+    }
+    Ok(meshes.into_iter().collect())
+}
+
+#[test]
+fn test_load_obj() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("second_weekend_obj_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("quads.mtl"),
+        "newmtl lamp\nKd 0 0 0\nKe 4 4 4\n\nnewmtl glass\nillum 7\nNi 1.5\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("quads.obj"),
+        "mtllib quads.mtl\n\
+         v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         v 0 0 2\nv 1 0 2\nv 1 1 2\nv 0 1 2\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+         g front\nusemtl lamp\nf 1/1 2/2 3/3 4/4\n\
+         g back\nusemtl glass\nf 5/1 6/2 7/3 8/4\n",
+    )
+    .unwrap();
+
+    let quads = load(&dir.join("quads.obj"), None).unwrap();
+    let bbox = quads.bounding_box(0.0, 1.0);
+    assert!((bbox.max.z() - 2.0).abs() < 1e-3);
+
+    let r = super::ray::Ray::new(Vec3::new(0.75, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+    let rec = quads.hit(&r, 0.0, 10.0).unwrap();
+    assert_eq!(rec.t, 1.0);
+    assert!((rec.u - 0.75).abs() < 1e-6 && (rec.v - 0.25).abs() < 1e-6);
+    assert!(0.0 < rec.material.emitted(rec.u, rec.v, &rec.p).x());
+
+    assert!(load(&dir.join("missing.obj"), None).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
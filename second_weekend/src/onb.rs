@@ -0,0 +1,36 @@
+use super::vec3::*;
+
+/// Orthonormal basis built around a given `w` axis.
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(n: &Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if 0.9 < w.x().abs() {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = unit_vector(&cross(&w, &a));
+        let u = cross(&w, &v);
+        Self { u, v, w }
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.w
+    }
+
+    /// Maps coordinates in this basis to world coordinates.
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+
+    /// Coordinates of the world vector `a` in this basis.
+    pub fn coords(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(dot(a, &self.u), dot(a, &self.v), dot(a, &self.w))
+    }
+}
@@ -0,0 +1,126 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use image::codecs::hdr::HdrEncoder;
+use image::{ImageError, Rgb, RgbImage};
+
+use super::tonemap::ToneMapping;
+use super::vec3::Vec3;
+
+/// Linear radiance of every pixel, row by row from the top.
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Png,
+    Ppm,
+    Hdr,
+}
+
+impl OutputFormat {
+    pub fn from_path(path: &Path) -> Result<Self, OutputError> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("png") => Ok(OutputFormat::Png),
+            Some("ppm") => Ok(OutputFormat::Ppm),
+            Some("hdr") => Ok(OutputFormat::Hdr),
+            _ => Err(OutputError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    UnsupportedFormat(String),
+    Io(io::Error),
+    Image(ImageError),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputError::UnsupportedFormat(path) => write!(
+                f,
+                "cannot infer an image format from `{}`, use .png, .ppm or .hdr",
+                path
+            ),
+            OutputError::Io(err) => write!(f, "{}", err),
+            OutputError::Image(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for OutputError {}
+
+impl From<io::Error> for OutputError {
+    fn from(err: io::Error) -> Self {
+        OutputError::Io(err)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(err: ImageError) -> Self {
+        OutputError::Image(err)
+    }
+}
+
+impl Framebuffer {
+    /// Display-ready 8-bit sRGB pixels, row by row from the top.
+    pub fn to_rgb8(&self, tone_mapping: &ToneMapping) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|col| tone_mapping.encode_srgb8(col))
+            .collect()
+    }
+
+    /// Writes the image, tone mapped unless the format keeps linear
+    /// radiance.
+    pub fn save(&self, path: &Path, tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+        match OutputFormat::from_path(path)? {
+            OutputFormat::Png => self.write_png(path, tone_mapping),
+            OutputFormat::Ppm => self.write_ppm(path, tone_mapping),
+            OutputFormat::Hdr => self.write_hdr(path),
+        }
+    }
+
+    fn write_png(&self, path: &Path, tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+        let img = RgbImage::from_raw(
+            self.width as u32,
+            self.height as u32,
+            self.to_rgb8(tone_mapping),
+        )
+        .expect("framebuffer size does not match its dimensions");
+        img.save(path)?;
+        Ok(())
+    }
+
+    fn write_ppm(&self, path: &Path, tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.to_rgb8(tone_mapping))?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Stores the unclamped linear radiance as a Radiance RGBE image.
+    fn write_hdr(&self, path: &Path) -> Result<(), OutputError> {
+        let out = BufWriter::new(File::create(path)?);
+        let data = self
+            .pixels
+            .iter()
+            .map(|col| Rgb([col.x().max(0.0), col.y().max(0.0), col.z().max(0.0)]))
+            .collect::<Vec<_>>();
+        HdrEncoder::new(out).encode(&data, self.width, self.height)?;
+        Ok(())
+    }
+}
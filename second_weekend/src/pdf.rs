@@ -0,0 +1,237 @@
+use std::f32::consts::PI;
+
+use super::environment::Environment;
+use super::hitable::Hitable;
+use super::onb::Onb;
+use super::ray::Ray;
+use super::sampler::Sampler;
+use super::vec3::*;
+
+/// A density over directions, with respect to solid angle.
+pub trait Pdf {
+    fn value(&self, direction: &Vec3) -> f32;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+/// Maps a point of the unit square to a direction of unit length, evenly
+/// over the sphere.
+pub fn uniform_sphere_direction((u1, u2): (f32, f32)) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Uniformly distributed direction of unit length, for checking densities
+/// in tests.
+#[cfg(test)]
+pub fn random_unit_vector() -> Vec3 {
+    use super::rng::random;
+
+    uniform_sphere_direction((random::<f32>(), random::<f32>()))
+}
+
+/// Density of `hitable`, a flat shape of the given `area`, being hit from
+/// `o` in direction `v`.
+pub fn planar_pdf_value(hitable: &dyn Hitable, area: f32, o: &Vec3, v: &Vec3) -> f32 {
+    match hitable.hit(&Ray::new(*o, *v, 0.0), 0.001, f32::MAX) {
+        Some(rec) => {
+            let distance_squared = rec.t * rec.t * v.squared_length();
+            let cosine = (dot(v, &rec.normal) / v.lenght()).abs();
+            distance_squared / (cosine * area)
+        }
+        None => 0.0,
+    }
+}
+
+fn random_cosine_direction((r1, r2): (f32, f32)) -> Vec3 {
+    let phi = 2.0 * PI * r1;
+    let z = (1.0 - r2).sqrt();
+    Vec3::new(phi.cos() * r2.sqrt(), phi.sin() * r2.sqrt(), z)
+}
+
+/// Directions around a normal, with density proportional to their cosine.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: &Vec3) -> Self {
+        Self {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f32 {
+        let cosine = dot(&unit_vector(direction), &self.uvw.w());
+        (cosine / PI).max(0.0)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.local(&random_cosine_direction(sampler.next_2d()))
+    }
+}
+
+/// Directions towards a point evenly spread over a ball of radius `fuzz`
+/// around the tip of `axis`, the way a fuzzy metal scatters its reflections.
+pub struct FuzzPdf {
+    axis: Vec3,
+    fuzz: f32,
+}
+
+impl FuzzPdf {
+    pub fn new(axis: &Vec3, fuzz: f32) -> Self {
+        Self {
+            axis: unit_vector(axis),
+            fuzz,
+        }
+    }
+}
+
+impl Pdf for FuzzPdf {
+    /// The ball's volume along the direction, `t^2 dt` from where it enters
+    /// to where it leaves, over its whole volume.
+    fn value(&self, direction: &Vec3) -> f32 {
+        let b = dot(&unit_vector(direction), &self.axis);
+        let discriminant = b * b - 1.0 + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let (t0, t1) = (b - discriminant.sqrt(), b + discriminant.sqrt());
+        if t1 <= 0.0 {
+            return 0.0;
+        }
+        let t0 = t0.max(0.0);
+        (t1 * t1 * t1 - t0 * t0 * t0) / (4.0 * PI * self.fuzz * self.fuzz * self.fuzz)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = uniform_sphere_direction(sampler.next_2d());
+        self.axis + self.fuzz * sampler.next_1d().cbrt() * direction
+    }
+}
+
+/// Directions from `origin` towards points on a hitable.
+pub struct HitablePdf<'a> {
+    origin: Vec3,
+    hitable: &'a dyn Hitable,
+}
+
+impl<'a> HitablePdf<'a> {
+    pub fn new(hitable: &'a dyn Hitable, origin: Vec3) -> Self {
+        Self { origin, hitable }
+    }
+}
+
+impl<'a> Pdf for HitablePdf<'a> {
+    fn value(&self, direction: &Vec3) -> f32 {
+        self.hitable.pdf_value(&self.origin, direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.hitable.random(&self.origin, sampler)
+    }
+}
+
+/// Directions drawn the way an environment samples itself.
+pub struct EnvironmentPdf<'a> {
+    environment: &'a dyn Environment,
+}
+
+impl<'a> EnvironmentPdf<'a> {
+    pub fn new(environment: &'a dyn Environment) -> Self {
+        Self { environment }
+    }
+}
+
+impl<'a> Pdf for EnvironmentPdf<'a> {
+    fn value(&self, direction: &Vec3) -> f32 {
+        self.environment.pdf_value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.environment.sample(sampler)
+    }
+}
+
+/// Even mix of two densities.
+pub struct MixturePdf<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A: Pdf, B: Pdf> MixturePdf<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<A: Pdf, B: Pdf> Pdf for MixturePdf<A, B> {
+    fn value(&self, direction: &Vec3) -> f32 {
+        0.5 * self.a.value(direction) + 0.5 * self.b.value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.next_1d() < 0.5 {
+            self.a.generate(sampler)
+        } else {
+            self.b.generate(sampler)
+        }
+    }
+}
+
+#[test]
+fn test_pdfs_integrate_to_one() {
+    use std::sync::Arc;
+
+    use super::hitable_list::HitableList;
+    use super::material::{Dielectric, DiffuseLight, Material};
+    use super::rect::XZRect;
+    use super::rng::reseed;
+    use super::sampler::Independent;
+    use super::sphere::Sphere;
+    use super::texture::ConstantTexture;
+
+    reseed(1);
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+        Vec3::new(1.0, 1.0, 1.0),
+    ))));
+    // Sees the whole face of a cube centered at the origin.
+    let face = XZRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, Arc::clone(&light));
+    let sphere = Sphere::new(Vec3::new(0.0, 0.0, -3.0), 2.0, Arc::clone(&light));
+    // Only the lamp of a list is sampled.
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let list = vec![
+        Box::new(Sphere::new(Vec3::new(0.0, 0.0, 3.0), 1.0, glass)) as Box<dyn Hitable>,
+        Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, -1.0, light)),
+    ]
+    .into_iter()
+    .collect::<HitableList>();
+    assert!(list.is_light());
+
+    let origin = Vec3::default();
+    let pdfs: Vec<Box<dyn Pdf>> = vec![
+        Box::new(CosinePdf::new(&Vec3::new(0.0, 1.0, 1.0))),
+        Box::new(FuzzPdf::new(&Vec3::new(1.0, -1.0, 0.0), 0.3)),
+        Box::new(FuzzPdf::new(&Vec3::new(0.0, 2.0, 0.0), 1.0)),
+        Box::new(HitablePdf::new(&face, origin)),
+        Box::new(HitablePdf::new(&sphere, origin)),
+        Box::new(HitablePdf::new(&list, origin)),
+    ];
+
+    let n = 200_000;
+    for pdf in pdfs.iter() {
+        let integral = (0..n)
+            .map(|_| pdf.value(&random_unit_vector()))
+            .sum::<f32>()
+            * 4.0
+            * PI
+            / n as f32;
+        assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+
+        let d = pdf.generate(&mut Independent);
+        assert!(0.0 < pdf.value(&d));
+    }
+}
@@ -1,6 +1,4 @@
-use rand::prelude::*;
-use rand::random;
-
+use super::rng::random;
 use super::vec3::*;
 
 fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
@@ -9,14 +7,15 @@ fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
     let ww = w * w * (3.0 - 2.0 * w);
 
     let mut accum = 0.0;
-    for i in 0..2 {
-        for j in 0..2 {
-            for k in 0..2 {
-                let weight = Vec3::new(u - i as f32, v - j as f32, w - k as f32);
-                accum += ((i as f32 * uu + (1 - i) as f32 * (1.0 - uu))
-                    * (j as f32 * vv + (1 - j) as f32 * (1.0 - vv))
-                    * (k as f32 * ww + (1 - k) as f32 * (1.0 - ww)))
-                    * dot(&c[i][j][k], &weight);
+    for (i, ci) in c.iter().enumerate() {
+        for (j, cij) in ci.iter().enumerate() {
+            for (k, cijk) in cij.iter().enumerate() {
+                let (i, j, k) = (i as f32, j as f32, k as f32);
+                let weight = Vec3::new(u - i, v - j, w - k);
+                accum += ((i * uu + (1.0 - i) * (1.0 - uu))
+                    * (j * vv + (1.0 - j) * (1.0 - vv))
+                    * (k * ww + (1.0 - k) * (1.0 - ww)))
+                    * dot(cijk, &weight);
             }
         }
     }
@@ -25,18 +24,21 @@ fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
 
 fn generate_perm() -> [u8; 256] {
     let mut tbl = [0u8; 256];
-    for i in 0..tbl.len() {
-        tbl[i] = i as u8;
+    for (i, entry) in tbl.iter_mut().enumerate() {
+        *entry = i as u8;
+    }
+    // Fisher-Yates, drawing from the seedable generator.
+    for i in (1..tbl.len()).rev() {
+        let j = (random::<f32>() * (i + 1) as f32) as usize;
+        tbl.swap(i, j.min(i));
     }
-    let mut rng = rand::thread_rng();
-    tbl.shuffle(&mut rng);
     tbl
 }
 
 fn generate() -> [Vec3; 256] {
     let mut tbl = [Vec3::default(); 256];
-    for i in 0..tbl.len() {
-        tbl[i] = Vec3::new(
+    for entry in tbl.iter_mut() {
+        *entry = Vec3::new(
             -1.0 + 2.0 * random::<f32>(),
             -1.0 + 2.0 * random::<f32>(),
             -1.0 + 2.0 * random::<f32>(),
@@ -45,17 +47,26 @@ fn generate() -> [Vec3; 256] {
     tbl
 }
 
-lazy_static::lazy_static! {
-    static ref RANFLOAT: [Vec3; 256] =  generate();
-    static ref PERM_X: [u8; 256] =  generate_perm();
-    static ref PERM_Y: [u8; 256] =  generate_perm();
-    static ref PERM_Z: [u8; 256] =  generate_perm();
+/// Gradient noise over random tables, drawn from the calling thread's
+/// generator when the noise is created.
+#[derive(Debug)]
+pub struct Perlin {
+    ranfloat: [Vec3; 256],
+    perm_x: [u8; 256],
+    perm_y: [u8; 256],
+    perm_z: [u8; 256],
 }
 
-#[derive(Debug, Default)]
-pub struct Perlin {}
-
 impl Perlin {
+    pub fn new() -> Self {
+        Self {
+            ranfloat: generate(),
+            perm_x: generate_perm(),
+            perm_y: generate_perm(),
+            perm_z: generate_perm(),
+        }
+    }
+
     pub fn noise(&self, p: &Vec3) -> f32 {
         let u = p.x() - p.x().floor();
         let v = p.y() - p.y().floor();
@@ -65,12 +76,12 @@ impl Perlin {
         let k = p.z().floor() as i32;
 
         let mut c = [[[Vec3::default(); 2]; 2]; 2];
-        for di in 0..2 {
-            for dj in 0..2 {
-                for dk in 0..2 {
-                    c[di][dj][dk] = RANFLOAT[(PERM_X[((i + di as i32) & 255) as usize]
-                        ^ PERM_Y[((j + dj as i32) & 255) as usize]
-                        ^ PERM_Z[((k + dk as i32) & 255) as usize])
+        for (di, ci) in c.iter_mut().enumerate() {
+            for (dj, cij) in ci.iter_mut().enumerate() {
+                for (dk, cijk) in cij.iter_mut().enumerate() {
+                    *cijk = self.ranfloat[(self.perm_x[((i + di as i32) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i32) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i32) & 255) as usize])
                         as usize]
                 }
             }
@@ -0,0 +1,202 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::aabb::AABB;
+use super::hitable::{HitRecord, Hitable};
+use super::material::Material;
+use super::onb::Onb;
+use super::pdf::planar_pdf_value;
+use super::ray::Ray;
+use super::sampler::Sampler;
+use super::vec3::*;
+
+/// Box around a disk of `radius` centered on `center`, facing `normal`.
+pub fn disk_bounds(center: &Vec3, normal: &Vec3, radius: f32) -> AABB {
+    let n = unit_vector(normal);
+    // Rects are padded the same way, so that flat shapes have some volume.
+    let extent = |a: f32| radius * (1.0 - a * a).max(0.0).sqrt() + 0.0001;
+    let e = Vec3::new(extent(n.x()), extent(n.y()), extent(n.z()));
+    AABB::new(*center - e, *center + e)
+}
+
+/// Angle around the z axis of a point in local coordinates, in [0, 1).
+pub fn azimuth(p: &Vec3) -> f32 {
+    (p.y().atan2(p.x()) / (2.0 * PI)).rem_euclid(1.0)
+}
+
+/// Where a ray crosses the plane through the origin of `frame`, as `t` and
+/// the local coordinates of the point.
+fn hit_frame_plane(
+    frame: &Onb,
+    origin: &Vec3,
+    r: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, Vec3)> {
+    let denom = dot(r.direction(), &frame.w());
+    if denom.abs() < 1e-8 {
+        return None;
+    }
+    let t = dot(&(*origin - *r.origin()), &frame.w()) / denom;
+    if t < t_min || t_max < t {
+        return None;
+    }
+    Some((t, frame.coords(&(r.point_at_parameter(t) - *origin))))
+}
+
+/// The infinite plane through `point` facing `normal`.
+///
+/// Texture coordinates repeat every unit along the plane.
+pub struct Plane {
+    point: Vec3,
+    frame: Onb,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        debug_assert!(0.0 < normal.squared_length(), "plane normal is zero");
+        Self {
+            point,
+            frame: Onb::from_w(&normal),
+            material,
+        }
+    }
+}
+
+impl Hitable for Plane {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t, local) = hit_frame_plane(&self.frame, &self.point, r, t_min, t_max)?;
+        Some(HitRecord {
+            t,
+            p: r.point_at_parameter(t),
+            u: local.x().rem_euclid(1.0),
+            v: local.y().rem_euclid(1.0),
+            normal: self.frame.w(),
+            material: Arc::clone(&self.material),
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        AABB::infinite()
+    }
+}
+
+/// A flat disk of `radius` around `center`, facing `normal`.
+pub struct Disk {
+    center: Vec3,
+    radius: f32,
+    frame: Onb,
+    material: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Arc<dyn Material>) -> Self {
+        debug_assert!(0.0 < normal.squared_length(), "disk normal is zero");
+        debug_assert!(0.0 <= radius, "disk radius is negative");
+        Self {
+            center,
+            radius,
+            frame: Onb::from_w(&normal),
+            material,
+        }
+    }
+}
+
+impl Hitable for Disk {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t, local) = hit_frame_plane(&self.frame, &self.center, r, t_min, t_max)?;
+        let dist = local.lenght();
+        if self.radius < dist {
+            return None;
+        }
+        Some(HitRecord {
+            t,
+            p: r.point_at_parameter(t),
+            u: azimuth(&local),
+            v: dist / self.radius,
+            normal: self.frame.w(),
+            material: Arc::clone(&self.material),
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        disk_bounds(&self.center, &self.frame.w(), self.radius)
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        planar_pdf_value(self, PI * self.radius * self.radius, o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let r = self.radius * a.sqrt();
+        let phi = 2.0 * PI * b;
+        self.center
+            + self
+                .frame
+                .local(&Vec3::new(r * phi.cos(), r * phi.sin(), 0.0))
+            - *o
+    }
+}
+
+#[test]
+fn test_plane_and_disk() {
+    use super::hitable_list::HitableList;
+    use super::material::DiffuseLight;
+    use super::pdf::random_unit_vector;
+    use super::rng::reseed;
+    use super::sampler::Independent;
+    use super::sphere::Sphere;
+    use super::texture::ConstantTexture;
+
+    let lamp: Arc<dyn Material> = Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+        Vec3::new(1.0, 1.0, 1.0),
+    ))));
+    // An unbounded plane shares the list with a sphere standing on it.
+    let world = vec![
+        Box::new(Plane::new(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Arc::clone(&lamp),
+        )) as Box<dyn Hitable>,
+        Box::new(Sphere::new(Vec3::default(), 1.0, Arc::clone(&lamp))),
+    ]
+    .into_iter()
+    .collect::<HitableList>();
+    assert!(!world.bounding_box(0.0, 1.0).is_bounded());
+    let r = Ray::new(Vec3::new(100.0, 10.0, 3.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = world.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 11.0).abs() < 1e-4 && rec.normal.y() == 1.0);
+    let r = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    assert!((world.hit(&r, 0.001, f32::MAX).unwrap().t - 9.0).abs() < 1e-4);
+
+    // A tilted disk, sampled as a light.
+    let disk = Disk::new(
+        Vec3::new(1.0, 2.0, 3.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        0.5,
+        lamp,
+    );
+    let b = disk.bounding_box(0.0, 1.0);
+    assert!((b.max.z() - 3.5).abs() < 1e-3 && (b.max.x() - 1.0 - 0.5f32.sqrt() / 2.0).abs() < 1e-3);
+    reseed(4);
+    let o = Vec3::new(-1.0, 0.0, 2.0);
+    let n = 200_000;
+    let integral = (0..n)
+        .map(|_| disk.pdf_value(&o, &random_unit_vector()))
+        .sum::<f32>()
+        * 4.0
+        * PI
+        / n as f32;
+    assert!((integral - 1.0).abs() < 0.05, "integral {}", integral);
+    for _ in 0..100 {
+        let v = disk.random(&o, &mut Independent);
+        let rec = disk.hit(&Ray::new(o, v, 0.0), 0.001, f32::MAX).unwrap();
+        assert!(rec.v <= 1.0 && 0.0 < disk.pdf_value(&o, &v));
+    }
+}
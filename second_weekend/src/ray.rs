@@ -2,25 +2,25 @@ use super::vec3::*;
 
 #[derive(Clone, Debug)]
 pub struct Ray {
-    A: Vec3,
-    B: Vec3,
+    a: Vec3,
+    b: Vec3,
     pub time: f32
 }
 
 impl Ray {
     pub fn new(a: Vec3, b: Vec3, time: f32) -> Self {
-        Self { A: a, B: b, time }
+        Self { a, b, time }
     }
 
     pub fn origin(&self) -> &Vec3 {
-        &self.A
+        &self.a
     }
 
     pub fn direction(&self) -> &Vec3 {
-        &self.B
+        &self.b
     }
 
     pub fn point_at_parameter(&self, t: f32) -> Vec3 {
-        self.A + t * self.B
+        self.a + t * self.b
     }
 }
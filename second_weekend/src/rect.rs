@@ -1,10 +1,12 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::aabb::AABB;
-use super::hitable::{HitRecord, Hitable};
+use super::hitable::{Crossing, HitRecord, Hitable};
 use super::material::Material;
+use super::pdf::planar_pdf_value;
 use super::ray::Ray;
-use super::vec3::Vec3;
+use super::sampler::{Independent, Sampler};
+use super::vec3::{cross, dot, unit_vector, Vec3};
 
 pub struct FlipNormal(Box<dyn Hitable>);
 impl FlipNormal {
@@ -15,15 +17,39 @@ impl FlipNormal {
 
 impl Hitable for FlipNormal {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        self.0.hit(r, t_min, t_max).map(|mut hit_record| {
-            hit_record.normal = -hit_record.normal;
-            hit_record
-        })
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.0
+            .hit_sampled(r, t_min, t_max, sampler)
+            .map(|mut hit_record| {
+                hit_record.normal = -hit_record.normal;
+                hit_record
+            })
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
         self.0.bounding_box(t0, t1)
     }
+
+    fn is_light(&self) -> bool {
+        self.0.is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.0.pdf_value(o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.0.random(o, sampler)
+    }
 }
 
 pub struct Translate {
@@ -35,27 +61,67 @@ impl Translate {
     pub fn new(offset: Vec3, hitable: Box<dyn Hitable>) -> Self {
         Self { offset, hitable }
     }
+
+    fn moved(&self, r: &Ray) -> Ray {
+        Ray::new(*r.origin() - self.offset, *r.direction(), r.time)
+    }
+
+    fn placed(&self, rec: HitRecord) -> HitRecord {
+        HitRecord {
+            p: rec.p + self.offset,
+            ..rec
+        }
+    }
 }
 
 impl Hitable for Translate {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let moved_r = Ray::new(*r.origin() - self.offset, *r.direction(), r.time);
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
         self.hitable
-            .hit(&moved_r, t_min, t_max)
-            .map(|rec| HitRecord {
-                t: rec.t,
-                p: rec.p + self.offset,
-                u: rec.u,
-                v: rec.v,
-                normal: rec.normal,
-                material: rec.material,
+            .hit_sampled(&self.moved(r), t_min, t_max, sampler)
+            .map(|rec| self.placed(rec))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.hitable.is_solid()
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        self.hitable
+            .crossings(&self.moved(r))
+            .into_iter()
+            .map(|c| Crossing {
+                hit: self.placed(c.hit),
+                entering: c.entering,
             })
+            .collect()
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
         let box_ = self.hitable.bounding_box(t0, t1);
         AABB::new(box_.min + self.offset, box_.max + self.offset)
     }
+
+    fn is_light(&self) -> bool {
+        self.hitable.is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.hitable.pdf_value(&(*o - self.offset), v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.hitable.random(&(*o - self.offset), sampler)
+    }
 }
 
 pub struct RotateY {
@@ -72,8 +138,16 @@ impl RotateY {
         let cos_theta = radians.cos();
 
         let aabb_box = hitable.bounding_box(0.0, 1.0);
-        let mut min = [std::f32::MAX, std::f32::MAX, std::f32::MAX];
-        let mut max = [-std::f32::MAX, -std::f32::MAX, -std::f32::MAX];
+        if !aabb_box.is_bounded() {
+            return Self {
+                hitable,
+                sin_theta,
+                cos_theta,
+                bbox: AABB::infinite(),
+            };
+        }
+        let mut min = [f32::MAX, f32::MAX, f32::MAX];
+        let mut max = [-f32::MAX, -f32::MAX, -f32::MAX];
         for i in 0..2 {
             for j in 0..2 {
                 for k in 0..2 {
@@ -104,41 +178,89 @@ impl RotateY {
     }
 }
 
+impl RotateY {
+    /// Rotates a vector from world space into the object's frame.
+    fn to_object(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() - self.sin_theta * v.z(),
+            v.y(),
+            self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() + self.sin_theta * v.z(),
+            v.y(),
+            -self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+
+    fn rotated(&self, r: &Ray) -> Ray {
+        Ray::new(
+            self.to_object(r.origin()),
+            self.to_object(r.direction()),
+            r.time,
+        )
+    }
+
+    fn placed(&self, rec: HitRecord) -> HitRecord {
+        HitRecord {
+            p: self.to_world(&rec.p),
+            normal: self.to_world(&rec.normal),
+            ..rec
+        }
+    }
+}
+
 impl Hitable for RotateY {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let rotated_origin_x = self.cos_theta * r.origin().x() + -self.sin_theta * r.origin().z();
-        let rotated_origin_z = self.sin_theta * r.origin().x() + self.cos_theta * r.origin().z();
-        let rotated_direction_x =
-            self.cos_theta * r.direction().x() + -self.sin_theta * r.direction().z();
-        let rotated_direction_z =
-            self.sin_theta * r.direction().x() + self.cos_theta * r.direction().z();
-        let rotated_r = Ray::new(
-            Vec3::new(rotated_origin_x, r.origin().y(), rotated_origin_z),
-            Vec3::new(rotated_direction_x, r.direction().y(), rotated_direction_z),
-            r.time,
-        );
-        self.hitable.hit(&rotated_r, t_min, t_max).map(|rec| {
-            let rotated_p_x = self.cos_theta * rec.p.x() + self.sin_theta * rec.p.z();
-            let rotated_p_z = -self.sin_theta * rec.p.x() + self.cos_theta * rec.p.z();
-            let rotated_normal_x =
-                self.cos_theta * rec.normal.x() + self.sin_theta * rec.normal.z();
-            let rotated_normal_z =
-                -self.sin_theta * rec.normal.x() + self.cos_theta * rec.normal.z();
-
-            HitRecord {
-                t: rec.t,
-                p: Vec3::new(rotated_p_x, rec.p.y(), rotated_p_z),
-                u: rec.u,
-                v: rec.v,
-                normal: Vec3::new(rotated_normal_x, rec.normal.y(), rotated_normal_z),
-                material: rec.material,
-            }
-        })
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.hitable
+            .hit_sampled(&self.rotated(r), t_min, t_max, sampler)
+            .map(|rec| self.placed(rec))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.hitable.is_solid()
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        self.hitable
+            .crossings(&self.rotated(r))
+            .into_iter()
+            .map(|c| Crossing {
+                hit: self.placed(c.hit),
+                entering: c.entering,
+            })
+            .collect()
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
         self.bbox.clone()
     }
+
+    fn is_light(&self) -> bool {
+        self.hitable.is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.hitable
+            .pdf_value(&self.to_object(o), &self.to_object(v))
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.to_world(&self.hitable.random(&self.to_object(o), sampler))
+    }
 }
 
 pub struct XYRect {
@@ -147,11 +269,11 @@ pub struct XYRect {
     y0: f32,
     y1: f32,
     k: f32,
-    material: Rc<dyn Material>,
+    material: Arc<dyn Material>,
 }
 
 impl XYRect {
-    pub fn new(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Rc<dyn Material>) -> Self {
+    pub fn new(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Arc<dyn Material>) -> Self {
         Self {
             x0,
             x1,
@@ -182,7 +304,7 @@ impl Hitable for XYRect {
             v,
             t,
             p,
-            material: Rc::clone(&self.material),
+            material: Arc::clone(&self.material),
             normal: Vec3::new(0.0, 0.0, 1.0),
         })
     }
@@ -193,6 +315,25 @@ impl Hitable for XYRect {
             Vec3::new(self.x1, self.y1, self.k + 0.0001),
         )
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        planar_pdf_value(self, area, o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let p = Vec3::new(
+            self.x0 + a * (self.x1 - self.x0),
+            self.y0 + b * (self.y1 - self.y0),
+            self.k,
+        );
+        p - *o
+    }
 }
 
 pub struct XZRect {
@@ -201,11 +342,11 @@ pub struct XZRect {
     z0: f32,
     z1: f32,
     k: f32,
-    material: Rc<dyn Material>,
+    material: Arc<dyn Material>,
 }
 
 impl XZRect {
-    pub fn new(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Rc<dyn Material>) -> Self {
+    pub fn new(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
         Self {
             x0,
             x1,
@@ -236,7 +377,7 @@ impl Hitable for XZRect {
             v,
             t,
             p,
-            material: Rc::clone(&self.material),
+            material: Arc::clone(&self.material),
             normal: Vec3::new(0.0, 1.0, 0.0),
         })
     }
@@ -247,6 +388,25 @@ impl Hitable for XZRect {
             Vec3::new(self.x1, self.k + 0.0001, self.z1),
         )
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        planar_pdf_value(self, area, o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let p = Vec3::new(
+            self.x0 + a * (self.x1 - self.x0),
+            self.k,
+            self.z0 + b * (self.z1 - self.z0),
+        );
+        p - *o
+    }
 }
 
 pub struct YZRect {
@@ -255,11 +415,11 @@ pub struct YZRect {
     z0: f32,
     z1: f32,
     k: f32,
-    material: Rc<dyn Material>,
+    material: Arc<dyn Material>,
 }
 
 impl YZRect {
-    pub fn new(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Rc<dyn Material>) -> Self {
+    pub fn new(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
         Self {
             y0,
             y1,
@@ -290,7 +450,7 @@ impl Hitable for YZRect {
             v,
             t,
             p,
-            material: Rc::clone(&self.material),
+            material: Arc::clone(&self.material),
             normal: Vec3::new(1.0, 0.0, 0.0),
         })
     }
@@ -301,4 +461,168 @@ impl Hitable for YZRect {
             Vec3::new(self.k + 0.0001, self.y1, self.z1),
         )
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        planar_pdf_value(self, area, o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let p = Vec3::new(
+            self.k,
+            self.y0 + a * (self.y1 - self.y0),
+            self.z0 + b * (self.z1 - self.z0),
+        );
+        p - *o
+    }
+}
+
+/// A parallelogram with a corner at `q` and sides along `u` and `v`, facing
+/// `u x v`.
+///
+/// Texture coordinates run from 0 to 1 along each side.
+pub struct Quad {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    // Projects a point on the plane onto `u` and `v`.
+    w: Vec3,
+    area: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = cross(&u, &v);
+        Self {
+            q,
+            u,
+            v,
+            normal: unit_vector(&n),
+            w: n / dot(&n, &n),
+            area: n.lenght(),
+            material,
+        }
+    }
+}
+
+impl Hitable for Quad {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denom = dot(&self.normal, r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = dot(&self.normal, &(self.q - *r.origin())) / denom;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let p = r.point_at_parameter(t);
+        let h = p - self.q;
+        let u = dot(&self.w, &cross(&h, &self.v));
+        let v = dot(&self.w, &cross(&self.u, &h));
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        Some(HitRecord {
+            u,
+            v,
+            t,
+            p,
+            material: Arc::clone(&self.material),
+            normal: self.normal,
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        let corners = [
+            self.q,
+            self.q + self.u,
+            self.q + self.v,
+            self.q + self.u + self.v,
+        ];
+        let mut min = [f32::MAX; 3];
+        let mut max = [-f32::MAX; 3];
+        for a in 0..3 {
+            for p in &corners {
+                min[a] = min[a].min(p[a]);
+                max[a] = max[a].max(p[a]);
+            }
+            // Same padding as the axis-aligned rects.
+            if max[a] - min[a] < 0.0002 {
+                min[a] -= 0.0001;
+                max[a] += 0.0001;
+            }
+        }
+        AABB::new(
+            Vec3::new(min[0], min[1], min[2]),
+            Vec3::new(max[0], max[1], max[2]),
+        )
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        planar_pdf_value(self, self.area, o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        self.q + a * self.u + b * self.v - *o
+    }
+}
+
+#[test]
+fn test_quad() {
+    use super::material::DiffuseLight;
+    use super::pdf::random_unit_vector;
+    use super::rng::reseed;
+    use super::sampler::Independent;
+    use super::texture::ConstantTexture;
+    use std::f32::consts::PI;
+
+    let lamp: Arc<dyn Material> = Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+        Vec3::new(1.0, 1.0, 1.0),
+    ))));
+    // A slanted parallelogram, tilted about x so it faces up and back.
+    let quad = Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, -1.0),
+        Arc::clone(&lamp),
+    );
+    let r = Ray::new(Vec3::new(1.5, 5.0, -0.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = quad.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.5).abs() < 1e-5, "{}", rec.t);
+    assert!((rec.u - 0.5).abs() < 1e-5 && (rec.v - 0.5).abs() < 1e-5);
+    let expected = Vec3::new(0.0, 1.0, 1.0) / 2f32.sqrt();
+    assert!((dot(&rec.normal, &expected) - 1.0).abs() < 1e-5);
+    // Past the slanted edge, though still inside the bounding box.
+    let r = Ray::new(Vec3::new(0.2, 5.0, -0.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    assert!(quad.hit(&r, 0.001, f32::MAX).is_none());
+    let b = quad.bounding_box(0.0, 1.0);
+    assert!(b.max.x() == 3.0 && b.min.z() == -1.0 && b.max.y() == 1.0);
+
+    reseed(5);
+    let o = Vec3::new(1.0, 3.0, 2.0);
+    let n = 200_000;
+    let integral = (0..n)
+        .map(|_| quad.pdf_value(&o, &random_unit_vector()))
+        .sum::<f32>()
+        * 4.0
+        * PI
+        / n as f32;
+    assert!((integral - 1.0).abs() < 0.05, "integral {}", integral);
+    for _ in 0..100 {
+        let v = quad.random(&o, &mut Independent);
+        assert!(0.0 < quad.pdf_value(&o, &v));
+    }
 }
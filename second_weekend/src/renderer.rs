@@ -0,0 +1,405 @@
+use rayon::prelude::*;
+
+use super::camera::Camera;
+use super::environment::Environment;
+use super::film::{Film, FilmTile, Filter};
+use super::hitable::{HitRecord, Hitable};
+use super::material::ScatterRecord;
+use super::output::Framebuffer;
+use super::pdf::{EnvironmentPdf, HitablePdf, MixturePdf, Pdf};
+use super::ray::Ray;
+use super::rng::{mix_seed, reseed};
+use super::sampler::{Sampler, SamplerKind};
+use super::vec3::Vec3;
+
+const TILE_SIZE: usize = 32;
+
+/// Balances two sampling strategies, as the weight of the one that drew a
+/// direction with density `a` against another that would have had `b`.
+fn power_heuristic(a: f32, b: f32) -> f32 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 <= 0.0 {
+        return 0.0;
+    }
+    a2 / (a2 + b2)
+}
+
+/// Every light that can be sampled from `origin`: the emitters of `world`,
+/// the environment, or an even mix of both.
+fn light_pdf<'a>(
+    world: &'a dyn Hitable,
+    environment: &'a dyn Environment,
+    origin: Vec3,
+) -> Option<Box<dyn Pdf + 'a>> {
+    let emitters = HitablePdf::new(world, origin);
+    let sky = EnvironmentPdf::new(environment);
+    match (world.is_light(), environment.is_light()) {
+        (true, true) => Some(Box::new(MixturePdf::new(emitters, sky))),
+        (true, false) => Some(Box::new(emitters)),
+        (false, true) => Some(Box::new(sky)),
+        (false, false) => None,
+    }
+}
+
+/// Weight of light that `ray` found after being drawn from a material's
+/// lobe with density `lobe_pdf`, against the chance of light sampling at
+/// that bounce having found it too.
+fn lobe_weight(
+    ray: &Ray,
+    lobe_pdf: Option<f32>,
+    world: &dyn Hitable,
+    environment: &dyn Environment,
+) -> f32 {
+    match lobe_pdf {
+        Some(lobe_pdf) => {
+            let light_pdf = light_pdf(world, environment, *ray.origin())
+                .map_or(0.0, |lights| lights.value(ray.direction()));
+            power_heuristic(lobe_pdf, light_pdf)
+        }
+        None => 1.0,
+    }
+}
+
+/// Light reaching `rec` straight from a direction sampled from `lights`,
+/// weighted by how much of it the material scatters back along `r` and by
+/// how unlikely sampling the material's `lobe` was to find it.
+fn direct_light(
+    r: &Ray,
+    rec: &HitRecord,
+    lobe: &dyn Pdf,
+    lights: &dyn Pdf,
+    world: &dyn Hitable,
+    environment: &dyn Environment,
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let direction = lights.generate(sampler);
+    let pdf_value = lights.value(&direction);
+    let shadow_ray = Ray::new(rec.p, direction, r.time);
+    let scattering_pdf = rec.material.scattering_pdf(r, rec, &shadow_ray);
+    if pdf_value <= 0.0 || scattering_pdf <= 0.0 {
+        return Vec3::default();
+    }
+
+    let incoming = match world.hit_sampled(&shadow_ray, 0.001, f32::MAX, sampler) {
+        Some(light) => light.material.emitted(light.u, light.v, &light.p),
+        None => environment.value(&direction),
+    };
+    let weight = power_heuristic(pdf_value, lobe.value(&direction));
+    weight * scattering_pdf * incoming / pdf_value
+}
+
+/// Radiance arriving along `r`, following one path through `world`.
+///
+/// `throughput` is the fraction of light found further down the path that
+/// makes it back along `r`. Once a path is `rr_depth` bounces long it
+/// survives each bounce with a probability following its throughput, and
+/// survivors are scaled up to make up for the ones that were dropped.
+///
+/// Rays that leave the scene see the environment. Light found by sampling a
+/// material's lobe is weighted against the chance of light sampling at that
+/// bounce having found it too. Camera rays and specular bounces always count
+/// it in full.
+fn color(
+    r: &Ray,
+    world: &dyn Hitable,
+    environment: &dyn Environment,
+    max_depth: u32,
+    rr_depth: u32,
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let mut radiance = Vec3::default();
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    let mut ray = r.clone();
+    let mut lobe_pdf: Option<f32> = None;
+    let mut depth = 0;
+    loop {
+        let rec = match world.hit_sampled(&ray, 0.001, f32::MAX, sampler) {
+            Some(rec) => rec,
+            None => {
+                let weight = lobe_weight(&ray, lobe_pdf, world, environment);
+                radiance += weight * throughput * environment.value(ray.direction());
+                break;
+            }
+        };
+        let mut emitted = rec.material.emitted(rec.u, rec.v, &rec.p);
+        if rec.material.is_emissive() {
+            emitted *= lobe_weight(&ray, lobe_pdf, world, environment);
+        }
+        radiance += throughput * emitted;
+        if max_depth <= depth {
+            break;
+        }
+
+        match rec.material.scatter(&ray, &rec, sampler) {
+            Some(ScatterRecord::Specular {
+                attenuation,
+                ray: scattered,
+            }) => {
+                throughput *= attenuation;
+                ray = scattered;
+                lobe_pdf = None;
+            }
+            Some(ScatterRecord::Lobe { attenuation, pdf }) => {
+                if let Some(lights) = light_pdf(world, environment, rec.p) {
+                    radiance += throughput
+                        * attenuation
+                        * direct_light(
+                            &ray,
+                            &rec,
+                            pdf.as_ref(),
+                            lights.as_ref(),
+                            world,
+                            environment,
+                            sampler,
+                        );
+                }
+
+                let direction = pdf.generate(sampler);
+                let pdf_value = pdf.value(&direction);
+                let scattered = Ray::new(rec.p, direction, ray.time);
+                let scattering_pdf = rec.material.scattering_pdf(&ray, &rec, &scattered);
+                if pdf_value <= 0.0 || scattering_pdf <= 0.0 {
+                    break;
+                }
+                throughput *= attenuation * scattering_pdf / pdf_value;
+                ray = scattered;
+                lobe_pdf = Some(pdf_value);
+            }
+            None => break,
+        }
+
+        depth += 1;
+        if rr_depth <= depth {
+            let survival = throughput
+                .x()
+                .max(throughput.y())
+                .max(throughput.z())
+                .min(1.0);
+            if survival <= sampler.next_1d() {
+                break;
+            }
+            throughput /= survival;
+        }
+    }
+    radiance
+}
+
+struct Tile {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+/// Splits the image into square tiles and renders them on a worker pool.
+///
+/// Every pixel reseeds the thread's generator from `seed` and its own index,
+/// so the result is identical whatever the number of threads.
+pub struct Renderer {
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub max_depth: u32,
+    pub rr_depth: u32,
+    pub sampler: SamplerKind,
+    pub filter: Filter,
+    pub seed: u64,
+}
+
+impl Renderer {
+    fn tiles(&self) -> Vec<Tile> {
+        let mut tiles = vec![];
+        for y0 in (0..self.height).step_by(TILE_SIZE) {
+            for x0 in (0..self.width).step_by(TILE_SIZE) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + TILE_SIZE).min(self.width),
+                    y1: (y0 + TILE_SIZE).min(self.height),
+                });
+            }
+        }
+        tiles
+    }
+
+    fn render_pixel(
+        &self,
+        cam: &Camera,
+        world: &dyn Hitable,
+        environment: &dyn Environment,
+        film_tile: &mut FilmTile,
+        i: usize,
+        j: usize,
+    ) {
+        let seed = self.seed ^ mix_seed((j * self.width + i) as u64);
+        reseed(seed);
+        let mut sampler = self.sampler.build(seed, self.samples);
+
+        for s in 0..self.samples {
+            sampler.start_sample(s);
+            let (du, dv) = sampler.next_2d();
+            let (px, py) = (i as f32 + du, j as f32 + dv);
+            // Image rows go top to bottom while the camera's v axis goes up.
+            let u = px / self.width as f32;
+            let v = 1.0 - py / self.height as f32;
+            let r = cam.get_ray(u, v, sampler.as_mut());
+            let col = color(
+                &r,
+                world,
+                environment,
+                self.max_depth,
+                self.rr_depth,
+                sampler.as_mut(),
+            );
+            film_tile.add_sample(px, py, col);
+        }
+    }
+
+    fn render_tile(
+        &self,
+        cam: &Camera,
+        world: &dyn Hitable,
+        environment: &dyn Environment,
+        film: &Film,
+        tile: &Tile,
+    ) -> FilmTile {
+        let mut film_tile = film.tile(tile.x0, tile.y0, tile.x1, tile.y1);
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                self.render_pixel(cam, world, environment, &mut film_tile, i, j);
+            }
+        }
+        film_tile
+    }
+
+    pub fn render(
+        &self,
+        cam: &Camera,
+        world: &dyn Hitable,
+        environment: &dyn Environment,
+    ) -> Framebuffer {
+        let mut film = Film::new(self.width, self.height, self.filter);
+        let rendered = self
+            .tiles()
+            .par_iter()
+            .map(|tile| self.render_tile(cam, world, environment, &film, tile))
+            .collect::<Vec<_>>();
+
+        // Tiles overlap by the filter radius; merging them in a fixed order
+        // keeps the sums independent of the thread count.
+        for film_tile in rendered {
+            film.merge(film_tile);
+        }
+        film.into_framebuffer()
+    }
+}
+
+#[test]
+fn test_russian_roulette_is_unbiased() {
+    use std::sync::Arc;
+
+    use super::environment::Constant;
+    use super::hitable_list::HitableList;
+    use super::material::{DiffuseLight, Lambertian, Material};
+    use super::rect::FlipNormal;
+    use super::sampler::Independent;
+    use super::sphere::Sphere;
+    use super::texture::ConstantTexture;
+
+    reseed(3);
+    let wall: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.5, 0.5, 0.5),
+    ))));
+    let lamp: Arc<dyn Material> = Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+        Vec3::new(1.0, 1.0, 1.0),
+    ))));
+    // A lamp inside a closed room, so every path bounces until it is cut off.
+    let world = vec![
+        Box::new(FlipNormal::new(Box::new(Sphere::new(
+            Vec3::default(),
+            10.0,
+            wall,
+        )))) as Box<dyn Hitable>,
+        Box::new(Sphere::new(Vec3::default(), 2.0, lamp)),
+    ]
+    .into_iter()
+    .collect::<HitableList>();
+
+    let black = Constant::new(Vec3::default());
+    let r = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+    let n = 20_000;
+    let mean = |rr_depth| {
+        (0..n)
+            .map(|_| color(&r, &world, &black, 50, rr_depth, &mut Independent).x())
+            .sum::<f32>()
+            / n as f32
+    };
+    let full = mean(50);
+    let roulette = mean(0);
+    assert!(
+        (roulette - full).abs() < 0.05 * full,
+        "{} != {}",
+        roulette,
+        full
+    );
+}
+
+#[test]
+fn test_same_seed_renders_same_image() {
+    use super::hitable_list::HitableList;
+    use super::scenes::SceneName;
+
+    // Where the random scene puts its spheres.
+    let layout = |seed: u64| {
+        reseed(seed);
+        SceneName::Random
+            .build()
+            .unwrap()
+            .objects
+            .iter()
+            .map(|object| {
+                let b = object.bounding_box(0.0, 1.0);
+                [
+                    b.min.x(),
+                    b.min.y(),
+                    b.min.z(),
+                    b.max.x(),
+                    b.max.y(),
+                    b.max.z(),
+                ]
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(layout(7), layout(7));
+    assert_ne!(layout(7), layout(8));
+
+    // The lit scene is textured with Perlin noise.
+    let render = |seed: u64, threads: usize| {
+        reseed(seed);
+        let scene = SceneName::SimpleLight.build().unwrap();
+        let cam = scene.camera.build(1.0);
+        let environment = scene.environment;
+        let world = scene.objects.into_iter().collect::<HitableList>();
+        let renderer = Renderer {
+            width: 16,
+            height: 16,
+            samples: 4,
+            max_depth: 8,
+            rr_depth: 2,
+            sampler: SamplerKind::default(),
+            filter: Filter::Mitchell,
+            seed,
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| renderer.render(&cam, &world, environment.as_ref()))
+            .pixels
+            .iter()
+            .map(|p| [p.x(), p.y(), p.z()])
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(render(7, 1), render(7, 3));
+    assert_ne!(render(7, 2), render(8, 2));
+}
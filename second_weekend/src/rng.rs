@@ -0,0 +1,32 @@
+use std::cell::RefCell;
+
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Draws a value from the calling thread's generator.
+///
+/// Each worker reseeds its generator per pixel with [`reseed`], so the
+/// sequence a pixel sees does not depend on which thread renders it.
+pub fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+pub fn reseed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// SplitMix64 finalizer, used to derive well-spread seeds from pixel indices.
+pub fn mix_seed(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,338 @@
+use super::rng::{mix_seed, random};
+
+/// Largest `f32` below one, so samples stay within [0, 1).
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Hands out the uniform numbers in [0, 1) that the samples of one pixel are
+/// built from.
+///
+/// Every call moves on to the next dimension of the current sample, so each
+/// use of randomness along a path gets its own dimension of the sequence.
+pub trait Sampler {
+    /// Starts sample `index` of the pixel, back at the first dimension.
+    fn start_sample(&mut self, index: usize);
+    fn next_1d(&mut self) -> f32;
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+/// Which sampler the pixels of a render use.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SamplerKind {
+    /// Uncorrelated random numbers.
+    Independent,
+    /// Jittered strata, shuffled independently per dimension.
+    Stratified,
+    /// Halton sequence, shifted per pixel.
+    Halton,
+    /// Owen scrambled Sobol sequence.
+    #[default]
+    Sobol,
+}
+
+impl SamplerKind {
+    /// A sampler for `samples` samples of the pixel identified by `seed`.
+    pub fn build(self, seed: u64, samples: usize) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(Independent),
+            SamplerKind::Stratified => Box::new(Stratified::new(seed, samples)),
+            SamplerKind::Halton => Box::new(Halton::new(seed)),
+            SamplerKind::Sobol => Box::new(Sobol::new(seed)),
+        }
+    }
+}
+
+fn to_unit(x: u32) -> f32 {
+    ((x >> 8) as f32 / (1 << 24) as f32).min(ONE_MINUS_EPSILON)
+}
+
+fn dimension_seed(seed: u64, dimension: usize) -> u32 {
+    mix_seed(seed ^ mix_seed(dimension as u64)) as u32
+}
+
+/// Draws from the thread's generator, as the renderer always used to.
+pub struct Independent;
+
+impl Sampler for Independent {
+    fn start_sample(&mut self, _index: usize) {}
+
+    fn next_1d(&mut self) -> f32 {
+        random()
+    }
+}
+
+/// Maps `i` to a position in `0..l` through a permutation chosen by `p`.
+/// See Kensler, "Correlated Multi-Jittered Sampling".
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+/// One sample per stratum of each dimension, with pairs of dimensions split
+/// into a square grid. The strata are visited in a different order in every
+/// dimension so that the dimensions stay uncorrelated.
+pub struct Stratified {
+    seed: u64,
+    samples: u32,
+    grid: u32,
+    index: u32,
+    dimension: usize,
+}
+
+impl Stratified {
+    pub fn new(seed: u64, samples: usize) -> Self {
+        Self {
+            seed,
+            samples: samples as u32,
+            grid: (samples as f32).sqrt().ceil() as u32,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let p = dimension_seed(self.seed, self.dimension);
+        self.dimension += 1;
+        let stratum = permute(self.index, self.samples, p);
+        ((stratum as f32 + random::<f32>()) / self.samples as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let p = dimension_seed(self.seed, self.dimension);
+        self.dimension += 2;
+        let cell = permute(self.index, self.grid * self.grid, p);
+        let x = (cell % self.grid) as f32 + random::<f32>();
+        let y = (cell / self.grid) as f32 + random::<f32>();
+        (
+            (x / self.grid as f32).min(ONE_MINUS_EPSILON),
+            (y / self.grid as f32).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+fn radical_inverse(base: u32, mut i: u32) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv = inv_base;
+    let mut result = 0.0;
+    while i != 0 {
+        result += (i % base) as f64 * inv;
+        i /= base;
+        inv *= inv_base;
+    }
+    result as f32
+}
+
+/// The Halton sequence, one prime base per dimension, with a random shift per
+/// pixel and dimension. Dimensions past the table of primes fall back to
+/// independent random numbers.
+pub struct Halton {
+    seed: u64,
+    index: u32,
+    dimension: usize,
+}
+
+impl Halton {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Halton {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        match PRIMES.get(dimension) {
+            Some(&base) => {
+                let shift = to_unit(dimension_seed(self.seed, dimension));
+                (radical_inverse(base, self.index) + shift)
+                    .fract()
+                    .min(ONE_MINUS_EPSILON)
+            }
+            None => random(),
+        }
+    }
+}
+
+/// The first two dimensions of the Sobol sequence, as 32 bit fractions.
+fn sobol(index: u32, dimension: usize) -> u32 {
+    if dimension == 0 {
+        return index.reverse_bits();
+    }
+    let mut v = 1 << 31;
+    let mut result = 0;
+    let mut i = index;
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+/// Owen scrambling in base two.
+/// See Burley, "Practical Hash-based Owen Scrambling".
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// Owen scrambled Sobol points, padded from the first two dimensions: every
+/// pair of dimensions shuffles the sample order and scrambles the points
+/// with seeds of its own.
+pub struct Sobol {
+    seed: u64,
+    index: u32,
+    dimension: usize,
+}
+
+impl Sobol {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn shuffled_index(&self) -> u32 {
+        nested_uniform_scramble(self.index, dimension_seed(self.seed, self.dimension))
+    }
+}
+
+impl Sampler for Sobol {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let index = self.shuffled_index();
+        let seed = mix_seed(self.seed ^ self.dimension as u64) as u32;
+        self.dimension += 1;
+        to_unit(nested_uniform_scramble(sobol(index, 0), seed))
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let index = self.shuffled_index();
+        let seed = mix_seed(self.seed ^ self.dimension as u64);
+        self.dimension += 2;
+        (
+            to_unit(nested_uniform_scramble(sobol(index, 0), seed as u32)),
+            to_unit(nested_uniform_scramble(
+                sobol(index, 1),
+                (seed >> 32) as u32,
+            )),
+        )
+    }
+}
+
+#[test]
+fn test_samplers_converge_faster_than_independent() {
+    use super::rng::reseed;
+
+    // Fraction of the unit square inside a quarter disc, like a pixel
+    // covering the edge of a sphere.
+    let exact = std::f32::consts::PI / 4.0;
+    let samples = 64;
+    let rms_error = |kind: SamplerKind| {
+        let pixels = 200;
+        let sum = (0..pixels)
+            .map(|pixel| {
+                reseed(pixel);
+                let mut sampler = kind.build(mix_seed(pixel), samples);
+                let mut hits = 0;
+                for s in 0..samples {
+                    sampler.start_sample(s);
+                    // Skip a few dimensions as a camera and a bounce would.
+                    sampler.next_2d();
+                    sampler.next_1d();
+                    let (x, y) = sampler.next_2d();
+                    assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                    if x * x + y * y < 1.0 {
+                        hits += 1;
+                    }
+                }
+                let error = hits as f32 / samples as f32 - exact;
+                error * error
+            })
+            .sum::<f32>();
+        (sum / pixels as f32).sqrt()
+    };
+
+    let independent = rms_error(SamplerKind::Independent);
+    for kind in [
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ] {
+        let error = rms_error(kind);
+        assert!(
+            error < 0.5 * independent,
+            "{:?}: {} vs {}",
+            kind,
+            error,
+            independent
+        );
+    }
+
+    let mut seen = (0..100).map(|i| permute(i, 100, 12345)).collect::<Vec<_>>();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..100).collect::<Vec<_>>());
+}
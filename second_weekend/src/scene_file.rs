@@ -0,0 +1,1516 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+use image::DynamicImage;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use toml::{Spanned, Table, Value};
+
+use super::aabb::AABB;
+use super::box_::Box_;
+use super::camera::CameraConfig;
+use super::constant_medium::ConstantMedium;
+use super::csg::{Csg, CsgOp};
+use super::cylinder::{Cone, Cylinder};
+use super::environment::{Constant, Environment, EnvironmentMap, Sky};
+use super::heightfield::Heightfield;
+use super::hitable::Hitable;
+use super::hitable_list::{HitableList, SplitStrategy};
+use super::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use super::moving_sphere::MovingSphere;
+use super::obj;
+use super::perlin::Perlin;
+use super::plane::{Disk, Plane};
+use super::rect::{FlipNormal, Quad, RotateY, Translate, XYRect, XZRect, YZRect};
+use super::scenes::Scene;
+use super::sdf::{
+    CapsuleSdf, Mandelbulb, Repeat, RoundBoxSdf, Sdf, SdfObject, SmoothSubtraction, SmoothUnion,
+    SphereSdf, TorusSdf,
+};
+use super::sphere::Sphere;
+use super::texture::{CheckerTexture, ConstantTexture, ImageTexture, NoiseTexture, Texture};
+use super::tlas::Tlas;
+use super::torus::Torus;
+use super::transform::{AnimatedTransform, Keyframe, Matrix4, Quaternion, Transform};
+use super::triangle::{Triangle, TriangleMesh};
+use super::vec3::{cross, Vec3};
+
+#[derive(Debug)]
+pub struct SceneError {
+    path: PathBuf,
+    line: Option<usize>,
+    message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    lookfrom: Option<[f32; 3]>,
+    lookat: Option<[f32; 3]>,
+    vup: Option<[f32; 3]>,
+    vfov: Option<f32>,
+    aperture: Option<f32>,
+    focus_dist: Option<f32>,
+    time0: Option<f32>,
+    time1: Option<f32>,
+}
+
+/// A texture given either inline as a constant color or by name.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureRef {
+    Color([f32; 3]),
+    Named(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Constant { color: [f32; 3] },
+    Checker { odd: TextureRef, even: TextureRef },
+    Noise { scale: f32 },
+    Image { path: PathBuf },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: [f32; 3],
+        #[serde(default)]
+        fuzz: f32,
+    },
+    Dielectric {
+        ref_idx: f32,
+    },
+    DiffuseLight {
+        emit: TextureRef,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    MovingSphere {
+        center0: [f32; 3],
+        center1: [f32; 3],
+        #[serde(default)]
+        time0: f32,
+        #[serde(default = "default_time1")]
+        time1: f32,
+        radius: f32,
+        material: String,
+    },
+    XyRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: String,
+    },
+    XzRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    YzRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    Quad {
+        q: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        material: String,
+    },
+    #[serde(rename = "box")]
+    Box_ {
+        p0: [f32; 3],
+        p1: [f32; 3],
+        material: String,
+    },
+    Plane {
+        point: [f32; 3],
+        normal: [f32; 3],
+        material: String,
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Cylinder {
+        p0: [f32; 3],
+        p1: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: String,
+    },
+    Cone {
+        base: [f32; 3],
+        apex: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: String,
+    },
+    Torus {
+        center: [f32; 3],
+        #[serde(default = "default_axis")]
+        axis: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+        material: String,
+    },
+    Triangle {
+        v0: [f32; 3],
+        v1: [f32; 3],
+        v2: [f32; 3],
+        normals: Option<[[f32; 3]; 3]>,
+        material: String,
+    },
+    Mesh {
+        positions: Vec<[f32; 3]>,
+        normals: Option<Vec<[f32; 3]>>,
+        uvs: Option<Vec<[f32; 2]>>,
+        indices: Vec<[usize; 3]>,
+        material: String,
+    },
+    Obj {
+        path: PathBuf,
+        material: Option<String>,
+    },
+    ConstantMedium {
+        density: f32,
+        material: String,
+        boundary: Box<ObjectDesc>,
+    },
+    /// Inside either of two closed objects.
+    Union {
+        a: Box<ObjectDesc>,
+        b: Box<ObjectDesc>,
+    },
+    /// Inside both of two closed objects.
+    Intersection {
+        a: Box<ObjectDesc>,
+        b: Box<ObjectDesc>,
+    },
+    /// Inside closed object `a` but not `b`.
+    Difference {
+        a: Box<ObjectDesc>,
+        b: Box<ObjectDesc>,
+    },
+    Translate {
+        offset: [f32; 3],
+        object: Box<ObjectDesc>,
+    },
+    RotateY {
+        angle: f32,
+        object: Box<ObjectDesc>,
+    },
+    FlipNormal {
+        object: Box<ObjectDesc>,
+    },
+    Transform {
+        transform: Vec<TransformStep>,
+        object: Box<ObjectDesc>,
+    },
+    /// An object moving through keyframes over the shutter interval.
+    Animated {
+        keyframes: Vec<KeyframeDesc>,
+        object: Box<ObjectDesc>,
+    },
+    /// A transformed reference to one of the scene's shared shapes.
+    Instance {
+        shape: String,
+        #[serde(default)]
+        transform: Vec<TransformStep>,
+    },
+    List {
+        objects: Vec<ObjectDesc>,
+    },
+    /// Terrain over `min..max` in x and z, rising from `min` to `max` in y.
+    Heightfield {
+        heights: HeightsDesc,
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    /// The surface where `distance` is zero, traced within `min..max`.
+    Sdf {
+        distance: SdfDesc,
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+}
+
+/// One step of a transform; steps apply to the object in the order given.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformStep {
+    Translate([f32; 3]),
+    Scale([f32; 3]),
+    Rotate(AxisAngle),
+    RotateX(f32),
+    RotateY(f32),
+    RotateZ(f32),
+    Matrix([[f32; 4]; 4]),
+}
+
+/// A rotation by `angle` degrees around `axis`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AxisAngle {
+    axis: [f32; 3],
+    angle: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDesc {
+    time: f32,
+    #[serde(default)]
+    translate: [f32; 3],
+    rotate: Option<AxisAngle>,
+    #[serde(default = "default_scale")]
+    scale: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum HeightsDesc {
+    /// The brightness of a grayscale image.
+    Image { path: PathBuf },
+    /// Perlin turbulence at `scale` times the world coordinates.
+    Noise {
+        scale: f32,
+        #[serde(default = "default_depth")]
+        depth: u32,
+        #[serde(default = "default_resolution")]
+        resolution: usize,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum SdfDesc {
+    Sphere {
+        #[serde(default)]
+        center: [f32; 3],
+        radius: f32,
+    },
+    RoundBox {
+        #[serde(default)]
+        center: [f32; 3],
+        half_extents: [f32; 3],
+        #[serde(default)]
+        radius: f32,
+    },
+    Torus {
+        #[serde(default)]
+        center: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Capsule {
+        a: [f32; 3],
+        b: [f32; 3],
+        radius: f32,
+    },
+    Mandelbulb {
+        #[serde(default = "default_power")]
+        power: f32,
+        #[serde(default = "default_iterations")]
+        iterations: usize,
+    },
+    SmoothUnion {
+        a: Box<SdfDesc>,
+        b: Box<SdfDesc>,
+        k: f32,
+    },
+    SmoothSubtraction {
+        a: Box<SdfDesc>,
+        b: Box<SdfDesc>,
+        k: f32,
+    },
+    Repeat {
+        period: [f32; 3],
+        distance: Box<SdfDesc>,
+    },
+}
+
+fn default_axis() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_capped() -> bool {
+    true
+}
+
+fn default_depth() -> u32 {
+    7
+}
+
+fn default_iterations() -> usize {
+    12
+}
+
+fn default_power() -> f32 {
+    8.0
+}
+
+fn default_resolution() -> usize {
+    256
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_time1() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum EnvironmentDesc {
+    Constant {
+        color: [f32; 3],
+    },
+    Sky,
+    Map {
+        path: PathBuf,
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+/// The top level of a scene file.
+///
+/// Entries are kept as raw tables so that each one can be decoded on its own
+/// and its errors reported at its own line.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: Option<Spanned<CameraDesc>>,
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<Table>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<Table>>,
+    #[serde(default)]
+    objects: Vec<Spanned<Table>>,
+    #[serde(default)]
+    shapes: BTreeMap<String, Spanned<Table>>,
+    environment: Option<Spanned<Table>>,
+}
+
+fn vec3(e: &[f32; 3]) -> Vec3 {
+    Vec3::new(e[0], e[1], e[2])
+}
+
+/// Why the shape described by `desc` has no surface to speak of, if it has
+/// none.
+fn degenerate(desc: &ObjectDesc) -> Option<&'static str> {
+    let zero = |e: &[f32; 3]| vec3(e).squared_length() == 0.0;
+    match desc {
+        ObjectDesc::Quad { u, v, .. } if cross(&vec3(u), &vec3(v)).squared_length() == 0.0 => {
+            Some("quad edges `u` and `v` must not be parallel")
+        }
+        ObjectDesc::Plane { normal, .. } | ObjectDesc::Disk { normal, .. } if zero(normal) => {
+            Some("`normal` must not be zero")
+        }
+        ObjectDesc::Disk { radius, .. } if *radius < 0.0 => Some("`radius` must not be negative"),
+        ObjectDesc::Cylinder { p0, p1, .. } if p0 == p1 => {
+            Some("cylinder ends `p0` and `p1` must differ")
+        }
+        ObjectDesc::Cone { base, apex, .. } if base == apex => {
+            Some("cone `base` and `apex` must differ")
+        }
+        ObjectDesc::Cylinder { radius, .. } | ObjectDesc::Cone { radius, .. } if *radius < 0.0 => {
+            Some("`radius` must not be negative")
+        }
+        ObjectDesc::Torus { axis, .. } if zero(axis) => Some("torus `axis` must not be zero"),
+        ObjectDesc::Torus {
+            major_radius,
+            minor_radius,
+            ..
+        } if *major_radius < 0.0 || *minor_radius < 0.0 => Some("torus radii must not be negative"),
+        _ => None,
+    }
+}
+
+fn sdf(desc: &SdfDesc) -> Box<dyn Sdf> {
+    match desc {
+        SdfDesc::Sphere { center, radius } => Box::new(SphereSdf::new(vec3(center), *radius)),
+        SdfDesc::RoundBox {
+            center,
+            half_extents,
+            radius,
+        } => Box::new(RoundBoxSdf::new(vec3(center), vec3(half_extents), *radius)),
+        SdfDesc::Torus {
+            center,
+            major_radius,
+            minor_radius,
+        } => Box::new(TorusSdf::new(vec3(center), *major_radius, *minor_radius)),
+        SdfDesc::Capsule { a, b, radius } => Box::new(CapsuleSdf::new(vec3(a), vec3(b), *radius)),
+        SdfDesc::Mandelbulb { power, iterations } => Box::new(Mandelbulb::new(*power, *iterations)),
+        SdfDesc::SmoothUnion { a, b, k } => Box::new(SmoothUnion::new(sdf(a), sdf(b), *k)),
+        SdfDesc::SmoothSubtraction { a, b, k } => {
+            Box::new(SmoothSubtraction::new(sdf(a), sdf(b), *k))
+        }
+        SdfDesc::Repeat { period, distance } => Box::new(Repeat::new(vec3(period), sdf(distance))),
+    }
+}
+
+/// What is wrong with the distance function described by `desc` or any of
+/// its parts, if anything.
+fn sdf_error(desc: &SdfDesc) -> Option<&'static str> {
+    match desc {
+        SdfDesc::Sphere { radius, .. }
+        | SdfDesc::RoundBox { radius, .. }
+        | SdfDesc::Capsule { radius, .. }
+            if *radius < 0.0 =>
+        {
+            Some("`radius` must not be negative")
+        }
+        SdfDesc::Torus {
+            major_radius,
+            minor_radius,
+            ..
+        } if *major_radius < 0.0 || *minor_radius < 0.0 => Some("torus radii must not be negative"),
+        SdfDesc::SmoothUnion { k, .. } | SdfDesc::SmoothSubtraction { k, .. } if *k <= 0.0 => {
+            Some("blend radius `k` must be positive")
+        }
+        SdfDesc::SmoothUnion { a, b, .. } | SdfDesc::SmoothSubtraction { a, b, .. } => {
+            sdf_error(a).or_else(|| sdf_error(b))
+        }
+        SdfDesc::Repeat { period, .. } if period.iter().any(|&p| p <= 0.0) => {
+            Some("repeat `period` must be positive along every axis")
+        }
+        SdfDesc::Repeat { distance, .. } => sdf_error(distance),
+        _ => None,
+    }
+}
+
+fn line_of(src: &str, span: &Range<usize>) -> usize {
+    src[..span.start].matches('\n').count() + 1
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    src: &'a str,
+    textures: BTreeMap<String, (Range<usize>, TextureDesc)>,
+    materials: BTreeMap<String, Arc<dyn Material>>,
+    shapes: BTreeMap<String, Arc<dyn Hitable>>,
+}
+
+impl<'a> Loader<'a> {
+    fn error(&self, span: Option<Range<usize>>, message: String) -> SceneError {
+        SceneError {
+            path: self.path.to_path_buf(),
+            line: span.map(|span| line_of(self.src, &span)),
+            message,
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, table: &Spanned<Table>) -> Result<T, SceneError> {
+        Value::Table(table.get_ref().clone())
+            .try_into()
+            .map_err(|err: toml::de::Error| {
+                self.error(Some(table.span()), err.message().to_string())
+            })
+    }
+
+    /// Builds a texture, following named references while rejecting cycles.
+    fn texture(
+        &self,
+        tex: &TextureRef,
+        span: &Range<usize>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Box<dyn Texture>, SceneError> {
+        let name = match tex {
+            TextureRef::Color(color) => return Ok(Box::new(ConstantTexture::new(vec3(color)))),
+            TextureRef::Named(name) => name,
+        };
+        let (span, desc) = self
+            .textures
+            .get(name)
+            .ok_or_else(|| self.error(Some(span.clone()), format!("unknown texture `{}`", name)))?;
+        if visiting.contains(name) {
+            return Err(self.error(
+                Some(span.clone()),
+                format!("texture `{}` refers to itself", name),
+            ));
+        }
+
+        visiting.push(name.clone());
+        let texture: Box<dyn Texture> = match desc {
+            TextureDesc::Constant { color } => Box::new(ConstantTexture::new(vec3(color))),
+            TextureDesc::Checker { odd, even } => Box::new(CheckerTexture::new(
+                self.texture(odd, span, visiting)?,
+                self.texture(even, span, visiting)?,
+            )),
+            TextureDesc::Noise { scale } => Box::new(NoiseTexture::new(*scale)),
+            TextureDesc::Image { path } => {
+                Box::new(ImageTexture::new(self.load_image(path, span)?.to_rgb8()))
+            }
+        };
+        visiting.pop();
+        Ok(texture)
+    }
+
+    /// Decodes the image at `path`, relative to the scene file.
+    fn load_image(&self, path: &Path, span: &Range<usize>) -> Result<DynamicImage, SceneError> {
+        let path = self
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(path);
+        ImageReader::open(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|reader| reader.decode().map_err(|err| err.to_string()))
+            .map_err(|err| {
+                self.error(
+                    Some(span.clone()),
+                    format!("cannot load `{}`: {}", path.display(), err),
+                )
+            })
+    }
+
+    fn environment(&self, table: &Spanned<Table>) -> Result<Box<dyn Environment>, SceneError> {
+        let environment: Box<dyn Environment> = match self.decode(table)? {
+            EnvironmentDesc::Constant { color } => Box::new(Constant::new(vec3(&color))),
+            EnvironmentDesc::Sky => Box::new(Sky::default()),
+            EnvironmentDesc::Map {
+                path,
+                rotation,
+                intensity,
+            } => {
+                let path = self
+                    .path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(path);
+                let map = EnvironmentMap::load(&path, rotation, intensity).map_err(|err| {
+                    self.error(
+                        Some(table.span()),
+                        format!("cannot load `{}`: {}", path.display(), err),
+                    )
+                })?;
+                Box::new(map)
+            }
+        };
+        Ok(environment)
+    }
+
+    fn material(&self, table: &Spanned<Table>) -> Result<Arc<dyn Material>, SceneError> {
+        let span = table.span();
+        let material: Arc<dyn Material> = match self.decode(table)? {
+            MaterialDesc::Lambertian { albedo } => Arc::new(Lambertian::new(self.texture(
+                &albedo,
+                &span,
+                &mut vec![],
+            )?)),
+            MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal::new(vec3(&albedo), fuzz)),
+            MaterialDesc::Dielectric { ref_idx } => Arc::new(Dielectric::new(ref_idx)),
+            MaterialDesc::DiffuseLight { emit } => Arc::new(DiffuseLight::new(self.texture(
+                &emit,
+                &span,
+                &mut vec![],
+            )?)),
+        };
+        Ok(material)
+    }
+
+    fn lookup_material(
+        &self,
+        name: &str,
+        span: &Range<usize>,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        self.materials
+            .get(name)
+            .map(Arc::clone)
+            .ok_or_else(|| self.error(Some(span.clone()), format!("unknown material `{}`", name)))
+    }
+
+    fn transform(
+        &self,
+        steps: &[TransformStep],
+        span: &Range<usize>,
+    ) -> Result<Matrix4, SceneError> {
+        let projective = steps.iter().any(|step| match step {
+            TransformStep::Matrix(m) => m[3] != [0.0, 0.0, 0.0, 1.0],
+            _ => false,
+        });
+        if projective {
+            return Err(self.error(
+                Some(span.clone()),
+                "transform matrix must have a last row of [0, 0, 0, 1]".to_string(),
+            ));
+        }
+        let matrix = steps.iter().fold(Matrix4::identity(), |m, step| {
+            let step = match step {
+                TransformStep::Translate(offset) => Matrix4::translate(&vec3(offset)),
+                TransformStep::Scale(factors) => Matrix4::scale(&vec3(factors)),
+                TransformStep::Rotate(r) => Matrix4::rotate(&vec3(&r.axis), r.angle),
+                TransformStep::RotateX(angle) => Matrix4::rotate(&Vec3::new(1.0, 0.0, 0.0), *angle),
+                TransformStep::RotateY(angle) => Matrix4::rotate(&Vec3::new(0.0, 1.0, 0.0), *angle),
+                TransformStep::RotateZ(angle) => Matrix4::rotate(&Vec3::new(0.0, 0.0, 1.0), *angle),
+                TransformStep::Matrix(m) => Matrix4 { m: *m },
+            };
+            step * m
+        });
+        if matrix.inverse().is_none() {
+            return Err(self.error(Some(span.clone()), "transform is singular".to_string()));
+        }
+        Ok(matrix)
+    }
+
+    fn instance(
+        &self,
+        shape: &str,
+        transform: &[TransformStep],
+        span: &Range<usize>,
+    ) -> Result<Transform, SceneError> {
+        let shape = self
+            .shapes
+            .get(shape)
+            .ok_or_else(|| self.error(Some(span.clone()), format!("unknown shape `{}`", shape)))?;
+        Ok(Transform::new(
+            self.transform(transform, span)?,
+            Arc::clone(shape),
+        ))
+    }
+
+    /// Builds an operand of a CSG operation, which has to bound a solid.
+    fn solid(
+        &self,
+        desc: &ObjectDesc,
+        span: &Range<usize>,
+    ) -> Result<Box<dyn Hitable>, SceneError> {
+        let object = self.object(desc, span)?;
+        if !object.is_solid() {
+            return Err(self.error(
+                Some(span.clone()),
+                "CSG needs closed solids: spheres, boxes, tori, capped cylinders and cones"
+                    .to_string(),
+            ));
+        }
+        Ok(object)
+    }
+
+    fn object(
+        &self,
+        desc: &ObjectDesc,
+        span: &Range<usize>,
+    ) -> Result<Box<dyn Hitable>, SceneError> {
+        if let Some(message) = degenerate(desc) {
+            return Err(self.error(Some(span.clone()), message.to_string()));
+        }
+        let object: Box<dyn Hitable> = match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => Box::new(Sphere::new(
+                vec3(center),
+                *radius,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Box::new(MovingSphere::new(
+                vec3(center0),
+                vec3(center1),
+                *time0,
+                *time1,
+                *radius,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Box::new(XYRect::new(
+                *x0,
+                *x1,
+                *y0,
+                *y1,
+                *k,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(XZRect::new(
+                *x0,
+                *x1,
+                *z0,
+                *z1,
+                *k,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(YZRect::new(
+                *y0,
+                *y1,
+                *z0,
+                *z1,
+                *k,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Quad { q, u, v, material } => Box::new(Quad::new(
+                vec3(q),
+                vec3(u),
+                vec3(v),
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Box_ { p0, p1, material } => Box::new(Box_::new(
+                vec3(p0),
+                vec3(p1),
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Plane {
+                point,
+                normal,
+                material,
+            } => Box::new(Plane::new(
+                vec3(point),
+                vec3(normal),
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => Box::new(Disk::new(
+                vec3(center),
+                vec3(normal),
+                *radius,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Cylinder {
+                p0,
+                p1,
+                radius,
+                capped,
+                material,
+            } => Box::new(Cylinder::new(
+                vec3(p0),
+                vec3(p1),
+                *radius,
+                *capped,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Cone {
+                base,
+                apex,
+                radius,
+                capped,
+                material,
+            } => Box::new(Cone::new(
+                vec3(base),
+                vec3(apex),
+                *radius,
+                *capped,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+                material,
+            } => Box::new(Torus::new(
+                vec3(center),
+                vec3(axis),
+                *major_radius,
+                *minor_radius,
+                self.lookup_material(material, span)?,
+            )),
+            ObjectDesc::Triangle {
+                v0,
+                v1,
+                v2,
+                normals,
+                material,
+            } => {
+                let material = self.lookup_material(material, span)?;
+                match normals {
+                    Some([n0, n1, n2]) => Box::new(Triangle::with_normals(
+                        [vec3(v0), vec3(v1), vec3(v2)],
+                        [vec3(n0), vec3(n1), vec3(n2)],
+                        material,
+                    )),
+                    None => Box::new(Triangle::new(vec3(v0), vec3(v1), vec3(v2), material)),
+                }
+            }
+            ObjectDesc::Mesh {
+                positions,
+                normals,
+                uvs,
+                indices,
+                material,
+            } => {
+                if indices.is_empty() {
+                    return Err(self.error(Some(span.clone()), "mesh has no faces".to_string()));
+                }
+                if let Some(&index) = indices.iter().flatten().find(|&&i| positions.len() <= i) {
+                    return Err(self.error(
+                        Some(span.clone()),
+                        format!(
+                            "mesh index {} out of range for {} positions",
+                            index,
+                            positions.len()
+                        ),
+                    ));
+                }
+                if let Some(normals) = normals.as_ref().filter(|n| n.len() != positions.len()) {
+                    return Err(self.error(
+                        Some(span.clone()),
+                        format!(
+                            "mesh has {} normals for {} positions",
+                            normals.len(),
+                            positions.len()
+                        ),
+                    ));
+                }
+                if let Some(uvs) = uvs.as_ref().filter(|uv| uv.len() != positions.len()) {
+                    return Err(self.error(
+                        Some(span.clone()),
+                        format!(
+                            "mesh has {} uvs for {} positions",
+                            uvs.len(),
+                            positions.len()
+                        ),
+                    ));
+                }
+                Box::new(TriangleMesh::new(
+                    positions.iter().map(vec3).collect(),
+                    normals.as_ref().map(|n| n.iter().map(vec3).collect()),
+                    uvs.clone(),
+                    indices.clone(),
+                    self.lookup_material(material, span)?,
+                ))
+            }
+            ObjectDesc::Obj { path, material } => {
+                let material = match material {
+                    Some(name) => Some(self.lookup_material(name, span)?),
+                    None => None,
+                };
+                let path = self
+                    .path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(path);
+                Box::new(
+                    obj::load(&path, material)
+                        .map_err(|err| self.error(Some(span.clone()), err.to_string()))?,
+                )
+            }
+            ObjectDesc::ConstantMedium {
+                density,
+                material,
+                boundary,
+            } => Box::new(ConstantMedium::new(
+                *density,
+                self.lookup_material(material, span)?,
+                self.object(boundary, span)?,
+            )),
+            ObjectDesc::Union { a, b } => Box::new(Csg::new(
+                CsgOp::Union,
+                self.solid(a, span)?,
+                self.solid(b, span)?,
+            )),
+            ObjectDesc::Intersection { a, b } => Box::new(Csg::new(
+                CsgOp::Intersection,
+                self.solid(a, span)?,
+                self.solid(b, span)?,
+            )),
+            ObjectDesc::Difference { a, b } => Box::new(Csg::new(
+                CsgOp::Difference,
+                self.solid(a, span)?,
+                self.solid(b, span)?,
+            )),
+            ObjectDesc::Translate { offset, object } => {
+                Box::new(Translate::new(vec3(offset), self.object(object, span)?))
+            }
+            ObjectDesc::RotateY { angle, object } => {
+                Box::new(RotateY::new(*angle, self.object(object, span)?))
+            }
+            ObjectDesc::FlipNormal { object } => {
+                Box::new(FlipNormal::new(self.object(object, span)?))
+            }
+            ObjectDesc::Transform { transform, object } => Box::new(Transform::new(
+                self.transform(transform, span)?,
+                Arc::from(self.object(object, span)?),
+            )),
+            ObjectDesc::Animated { keyframes, object } => {
+                if keyframes.is_empty() {
+                    return Err(self.error(Some(span.clone()), "no keyframes".to_string()));
+                }
+                if keyframes.iter().any(|k| k.scale.contains(&0.0)) {
+                    return Err(
+                        self.error(Some(span.clone()), "keyframe has zero scale".to_string())
+                    );
+                }
+                let first = keyframes[0].scale;
+                if keyframes
+                    .iter()
+                    .any(|k| k.scale.iter().zip(&first).any(|(a, b)| a * b < 0.0))
+                {
+                    return Err(self.error(
+                        Some(span.clone()),
+                        "keyframe scales must keep their signs".to_string(),
+                    ));
+                }
+                let keyframes = keyframes
+                    .iter()
+                    .map(|k| Keyframe {
+                        time: k.time,
+                        translation: vec3(&k.translate),
+                        rotation: k.rotate.as_ref().map_or(Quaternion::identity(), |r| {
+                            Quaternion::from_axis_angle(&vec3(&r.axis), r.angle)
+                        }),
+                        scale: vec3(&k.scale),
+                    })
+                    .collect();
+                Box::new(AnimatedTransform::new(
+                    keyframes,
+                    Arc::from(self.object(object, span)?),
+                ))
+            }
+            ObjectDesc::Instance { shape, transform } => {
+                Box::new(self.instance(shape, transform, span)?)
+            }
+            ObjectDesc::List { objects } => {
+                if objects.is_empty() {
+                    return Err(self.error(Some(span.clone()), "empty object list".to_string()));
+                }
+                let objects = objects
+                    .iter()
+                    .map(|object| self.object(object, span))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Box::new(objects.into_iter().collect::<HitableList>())
+            }
+            ObjectDesc::Heightfield {
+                heights,
+                min,
+                max,
+                material,
+            } => {
+                if max[0] <= min[0] || max[2] <= min[2] {
+                    return Err(self.error(
+                        Some(span.clone()),
+                        "heightfield `min` must be below `max` in x and z".to_string(),
+                    ));
+                }
+                let too_small = |nx, nz| {
+                    self.error(
+                        Some(span.clone()),
+                        format!("heightfield needs at least 2x2 samples, got {}x{}", nx, nz),
+                    )
+                };
+                let material = self.lookup_material(material, span)?;
+                Box::new(match heights {
+                    HeightsDesc::Image { path } => {
+                        let image = self.load_image(path, span)?.to_luma8();
+                        let (nx, nz) = image.dimensions();
+                        if nx < 2 || nz < 2 {
+                            return Err(too_small(nx as usize, nz as usize));
+                        }
+                        Heightfield::from_image(&image, vec3(min), vec3(max), material)
+                    }
+                    HeightsDesc::Noise { resolution, .. } if *resolution < 2 => {
+                        return Err(too_small(*resolution, *resolution));
+                    }
+                    HeightsDesc::Noise {
+                        scale,
+                        depth,
+                        resolution,
+                    } => Heightfield::from_noise(
+                        &Perlin::new(),
+                        *scale,
+                        *depth,
+                        *resolution,
+                        vec3(min),
+                        vec3(max),
+                        material,
+                    ),
+                })
+            }
+            ObjectDesc::Sdf {
+                distance,
+                min,
+                max,
+                material,
+            } => {
+                if min.iter().zip(max).any(|(lo, hi)| hi < lo) {
+                    return Err(self.error(
+                        Some(span.clone()),
+                        "sdf `min` must not be above `max`".to_string(),
+                    ));
+                }
+                if let Some(message) = sdf_error(distance) {
+                    return Err(self.error(Some(span.clone()), message.to_string()));
+                }
+                Box::new(SdfObject::new(
+                    sdf(distance),
+                    AABB::new(vec3(min), vec3(max)),
+                    self.lookup_material(material, span)?,
+                ))
+            }
+        };
+        Ok(object)
+    }
+}
+
+/// Builds a scene from the TOML description in `src`.
+///
+/// `path` is only used for error messages and to resolve image textures.
+pub fn parse(path: &Path, src: &str) -> Result<Scene, SceneError> {
+    let desc = toml::from_str::<SceneDesc>(src).map_err(|err| SceneError {
+        path: path.to_path_buf(),
+        line: err.span().map(|span| line_of(src, &span)),
+        message: err.message().to_string(),
+    })?;
+
+    let mut loader = Loader {
+        path,
+        src,
+        textures: BTreeMap::new(),
+        materials: BTreeMap::new(),
+        shapes: BTreeMap::new(),
+    };
+    for (name, texture) in desc.textures.iter() {
+        let texture = (texture.span(), loader.decode(texture)?);
+        loader.textures.insert(name.clone(), texture);
+    }
+    for (name, material) in desc.materials.iter() {
+        let material = loader.material(material)?;
+        loader.materials.insert(name.clone(), material);
+    }
+
+    for (name, shape) in desc.shapes.iter() {
+        let shape = loader.object(&loader.decode(shape)?, &shape.span())?;
+        loader.shapes.insert(name.clone(), Arc::from(shape));
+    }
+
+    if desc.objects.is_empty() {
+        return Err(loader.error(None, "scene has no objects".to_string()));
+    }
+    // Top-level instances share one hierarchy over their shapes, the other
+    // objects stand on their own.
+    let mut objects = vec![];
+    let mut instances = vec![];
+    for object in desc.objects.iter() {
+        match loader.decode(object)? {
+            ObjectDesc::Instance { shape, transform } => {
+                instances.push(loader.instance(&shape, &transform, &object.span())?)
+            }
+            desc => objects.push(loader.object(&desc, &object.span())?),
+        }
+    }
+    if !instances.is_empty() {
+        objects.push(Box::new(Tlas::new(instances, SplitStrategy::default())));
+    }
+
+    let environment = match &desc.environment {
+        Some(environment) => loader.environment(environment)?,
+        None => Box::new(Constant::new(Vec3::default())),
+    };
+
+    let defaults = CameraConfig::default();
+    let no_camera = CameraDesc::default();
+    let cam = desc.camera.as_ref().map_or(&no_camera, Spanned::get_ref);
+    let camera = CameraConfig {
+        lookfrom: cam.lookfrom.as_ref().map_or(defaults.lookfrom, vec3),
+        lookat: cam.lookat.as_ref().map_or(defaults.lookat, vec3),
+        vup: cam.vup.as_ref().map_or(defaults.vup, vec3),
+        vfov: cam.vfov.unwrap_or(defaults.vfov),
+        aperture: cam.aperture.unwrap_or(defaults.aperture),
+        focus_dist: cam.focus_dist.unwrap_or(defaults.focus_dist),
+        time0: cam.time0.unwrap_or(defaults.time0),
+        time1: cam.time1.unwrap_or(defaults.time1),
+    };
+    // Acceleration structures bound motion over times 0 to 1 only.
+    if !(0.0 <= camera.time0 && camera.time0 <= camera.time1 && camera.time1 <= 1.0) {
+        return Err(loader.error(
+            desc.camera.as_ref().map(Spanned::span),
+            format!(
+                "camera shutter {}..{} is not within 0..1",
+                camera.time0, camera.time1
+            ),
+        ));
+    }
+    Ok(Scene {
+        objects,
+        camera,
+        environment,
+    })
+}
+
+pub fn load(path: &Path) -> Result<Scene, SceneError> {
+    let src = fs::read_to_string(path).map_err(|err| SceneError {
+        path: path.to_path_buf(),
+        line: None,
+        message: err.to_string(),
+    })?;
+    parse(path, &src)
+}
+
+#[test]
+fn test_parse_scene() {
+    let src = r#"
+[camera]
+lookfrom = [0, 0, 5]
+
+[environment]
+type = "constant"
+color = [0.5, 0.5, 0.5]
+
+[textures.checker]
+type = "checker"
+odd = [0.0, 0.0, 0.0]
+even = "white"
+
+[textures.white]
+type = "constant"
+color = [1, 1, 1]
+
+[materials.floor]
+type = "lambertian"
+albedo = "checker"
+
+[materials.lamp]
+type = "diffuse_light"
+emit = [4, 4, 4]
+
+[[objects]]
+type = "plane"
+point = [0, 0, 0]
+normal = [0, 1, 0]
+material = "floor"
+
+[[objects]]
+type = "translate"
+offset = [0, 2, 0]
+
+[objects.object]
+type = "flip_normal"
+object = { type = "xz_rect", x0 = -1, x1 = 1, z0 = -1, z1 = 1, k = 0, material = "lamp" }
+
+[[objects]]
+type = "mesh"
+positions = [[0, 0, 0], [1, 0, 0], [1, 1, 0], [0, 1, 0]]
+indices = [[0, 1, 2], [0, 2, 3]]
+material = "floor"
+
+[shapes.crate]
+type = "box"
+p0 = [-1, 0, -1]
+p1 = [1, 2, 1]
+material = "floor"
+
+[[objects]]
+type = "instance"
+shape = "crate"
+transform = [{ scale = [2, 2, 2] }, { rotate_y = 45 }, { translate = [0, 0, -10] }]
+
+[[objects]]
+type = "instance"
+shape = "crate"
+transform = [{ rotate = { axis = [1, 0, 0], angle = 90 } }, { translate = [0, 0, 20] }]
+
+[[objects]]
+type = "animated"
+keyframes = [
+    { time = 0, translate = [0, 5, 0] },
+    { time = 1, translate = [0, 6, 0], rotate = { axis = [0, 1, 0], angle = 30 }, scale = [2, 1, 1] },
+]
+object = { type = "box", p0 = [-1, -1, -1], p1 = [1, 1, 1], material = "floor" }
+"#;
+    let scene = parse(Path::new("test.toml"), src).unwrap();
+    assert_eq!(scene.camera.lookfrom.z(), 5.0);
+    assert_eq!(scene.camera.vfov, CameraConfig::default().vfov);
+    assert!(scene.environment.is_light());
+    let moving = scene.objects[3].bounding_box(0.0, 1.0);
+    assert!(moving.min.y() <= 4.0 && 7.0 <= moving.max.y());
+    // Both crates end up in one hierarchy, the first one's corners
+    // 2 * sqrt(2) from its center.
+    let crates = scene.objects[4].bounding_box(0.0, 1.0);
+    assert!((crates.min.z() + 10.0 + 8f32.sqrt()).abs() < 1e-4);
+    assert!((crates.max.z() - 22.0).abs() < 1e-4);
+    let world = scene.objects.into_iter().collect::<HitableList>();
+    assert!(!world.bounding_box(0.0, 1.0).is_bounded());
+}
+
+#[test]
+fn test_parse_errors_report_lines() {
+    let line_of = |src: &str| match parse(Path::new("test.toml"), src) {
+        Ok(_) => panic!("expected an error for {}", src),
+        Err(err) => err.line,
+    };
+
+    let missing_field = "[[objects]]\ntype = \"sphere\"\nmaterial = \"m\"\n";
+    assert_eq!(line_of(missing_field), Some(1));
+
+    let unknown_material = r#"
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "m"
+
+[[objects]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "n"
+"#;
+    assert_eq!(line_of(unknown_material), Some(12));
+    let bad_type = unknown_material.replace("radius = 1\nmaterial = \"n\"", "radius = \"1\"");
+    assert_eq!(line_of(&bad_type), Some(12));
+
+    let texture_cycle = r#"
+[textures.a]
+type = "checker"
+odd = "a"
+even = [1, 1, 1]
+
+[materials.m]
+type = "lambertian"
+albedo = "a"
+"#;
+    assert_eq!(line_of(texture_cycle), Some(2));
+
+    let singular = r#"
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[shapes.ball]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "m"
+
+[[objects]]
+type = "instance"
+shape = "ball"
+transform = [{ scale = [1, 0, 1] }]
+"#;
+    assert_eq!(line_of(singular), Some(12));
+    let long_shutter = r#"
+[camera]
+time0 = 0.5
+time1 = 2
+
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "m"
+"#;
+    assert_eq!(line_of(long_shutter), Some(2));
+    let projective = singular.replace(
+        "{ scale = [1, 0, 1] }",
+        "{ matrix = [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0.5, 1]] }",
+    );
+    assert_eq!(line_of(&projective), Some(12));
+    let flattened = singular.replace(
+        "{ scale = [1, 0, 1] }",
+        "{ matrix = [[1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]] }",
+    );
+    assert_eq!(line_of(&flattened), Some(12));
+    assert_eq!(
+        line_of(&singular.replace("\"ball\"\n", "\"bal\"\n")),
+        Some(12)
+    );
+
+    let flat = r#"
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "heightfield"
+heights = { type = "noise", scale = 1, resolution = 1 }
+min = [0, 0, 0]
+max = [1, 1, 1]
+material = "m"
+"#;
+    assert_eq!(line_of(flat), Some(6));
+    let swapped = flat
+        .replace("resolution = 1", "resolution = 4")
+        .replace("max = [1, 1, 1]", "max = [-1, 1, 1]");
+    assert_eq!(line_of(&swapped), Some(6));
+
+    let open = r#"
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "difference"
+a = { type = "sphere", center = [0, 0, 0], radius = 1, material = "m" }
+b = { type = "plane", point = [0, 0, 0], normal = [0, 1, 0], material = "m" }
+"#;
+    assert_eq!(line_of(open), Some(6));
+    let mirrored = r#"
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "animated"
+keyframes = [{ time = 0, scale = [1, 1, 1] }, { time = 1, scale = [-1, 1, 1] }]
+object = { type = "sphere", center = [0, 0, 0], radius = 1, material = "m" }
+"#;
+    assert_eq!(line_of(mirrored), Some(6));
+    assert!(parse(
+        Path::new("test.toml"),
+        &mirrored.replace("[1, 1, 1]", "[-2, 1, 1]")
+    )
+    .is_ok());
+
+    let sdf = r#"
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "sdf"
+min = [-2, -2, -2]
+max = [2, 2, 2]
+material = "m"
+distance = { type = "smooth_union", k = 0.5, a = { type = "sphere", radius = 1 }, b = { type = "repeat", period = [1, 1, 1], distance = { type = "sphere", radius = 0.2 } } }
+"#;
+    assert!(parse(Path::new("test.toml"), sdf).is_ok());
+    for (from, to) in [
+        ("k = 0.5", "k = 0"),
+        ("radius = 1 }", "radius = -1 }"),
+        ("period = [1, 1, 1]", "period = [1, 0, 1]"),
+        ("max = [2, 2, 2]", "max = [2, -3, 2]"),
+    ]
+    .iter()
+    {
+        assert_eq!(line_of(&sdf.replace(from, to)), Some(6), "{}", to);
+    }
+
+    let zero_normal = open.replace("normal = [0, 1, 0]", "normal = [0, 0, 0]");
+    assert_eq!(line_of(&zero_normal), Some(6));
+
+    let degenerate = [
+        "type = \"quad\"\nq = [0, 0, 0]\nu = [1, 0, 0]\nv = [2, 0, 0]",
+        "type = \"disk\"\ncenter = [0, 0, 0]\nnormal = [0, 1, 0]\nradius = -1",
+        "type = \"cylinder\"\np0 = [0, 1, 0]\np1 = [0, 1, 0]\nradius = 1",
+        "type = \"cone\"\nbase = [0, 0, 0]\napex = [0, 1, 0]\nradius = -1",
+        "type = \"torus\"\ncenter = [0, 0, 0]\naxis = [0, 0, 0]\nmajor_radius = 2\nminor_radius = 1",
+    ];
+    for object in degenerate.iter() {
+        let src = format!(
+            "[materials.m]\ntype = \"dielectric\"\nref_idx = 1.5\n\n[[objects]]\n{}\nmaterial = \"m\"\n",
+            object
+        );
+        assert_eq!(line_of(&src), Some(5), "{}", object);
+    }
+
+    let dir = std::env::temp_dir().join(format!("second_weekend_env_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("empty.hdr"),
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 0 +X 0\n",
+    )
+    .unwrap();
+    let empty_map = r#"
+[environment]
+type = "map"
+path = "empty.hdr"
+
+[materials.m]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "m"
+"#;
+    let err = parse(&dir.join("scene.toml"), empty_map).err().unwrap();
+    assert_eq!(err.line, Some(2), "{}", err);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let syntax = "[camera]\nvfov = \n";
+    assert_eq!(line_of(syntax), Some(2));
+}
@@ -0,0 +1,454 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+use image::{ImageError, RgbImage};
+
+use super::box_::Box_;
+use super::camera::CameraConfig;
+use super::constant_medium::ConstantMedium;
+use super::environment::{Constant, Environment, Sky};
+use super::hitable::Hitable;
+use super::hitable_list::{HitableList, SplitStrategy};
+use super::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use super::moving_sphere::MovingSphere;
+use super::plane::Plane;
+use super::rect::{FlipNormal, RotateY, Translate, XYRect, XZRect, YZRect};
+use super::rng::random;
+use super::sphere::Sphere;
+use super::texture::{CheckerTexture, ConstantTexture, ImageTexture, NoiseTexture};
+use super::tlas::Tlas;
+use super::transform::{Matrix4, Transform};
+use super::vec3::Vec3;
+
+/// The top-level objects of a world together with the camera it is meant to
+/// be viewed from and what lies beyond them.
+pub struct Scene {
+    pub objects: Vec<Box<dyn Hitable>>,
+    pub camera: CameraConfig,
+    pub environment: Box<dyn Environment>,
+}
+
+/// Built-in scenes selectable from the command line.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum SceneName {
+    Random,
+    Simple,
+    SimpleLight,
+    CornellBox,
+    Final,
+}
+
+impl SceneName {
+    pub fn build(self) -> Result<Scene, Box<dyn Error>> {
+        match self {
+            SceneName::Random => Ok(random_scene()),
+            SceneName::Simple => simple_scene(),
+            SceneName::SimpleLight => Ok(simple_light()),
+            SceneName::CornellBox => Ok(cornel_box()),
+            SceneName::Final => second_weekend_final(),
+        }
+    }
+}
+
+const TEXTURE: &str = "./assets/texture.jpg";
+
+fn load_texture() -> Result<RgbImage, Box<dyn Error>> {
+    ImageReader::open(TEXTURE)
+        .map_err(ImageError::from)
+        .and_then(|reader| reader.decode())
+        .map(|img| img.to_rgb8())
+        .map_err(|err| format!("cannot load `{}`: {}", TEXTURE, err).into())
+}
+
+fn random_scene() -> Scene {
+    let checker = CheckerTexture::new(
+        Box::new(ConstantTexture::new(Vec3::new(0.2, 0.3, 0.1))),
+        Box::new(ConstantTexture::new(Vec3::new(0.9, 0.9, 0.9))),
+    );
+    let mut world: Vec<Box<dyn Hitable>> = vec![Box::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(Box::new(checker))),
+    ))];
+    for a in -15..15 {
+        for b in -15..15 {
+            let radius = 0.1 + 0.25 * random::<f32>();
+            let center = Vec3::new(
+                a as f32 + 0.9 * random::<f32>(),
+                radius,
+                b as f32 + 0.9 * random::<f32>(),
+            );
+            let material_choice = random::<f32>();
+            let material: Arc<dyn Material> = if material_choice < 0.8 {
+                Arc::new(Lambertian::new(Box::new(ConstantTexture::new(Vec3::new(
+                    random::<f32>() * random::<f32>(),
+                    random::<f32>() * random::<f32>(),
+                    random::<f32>() * random::<f32>(),
+                )))))
+            } else if material_choice < 0.95 {
+                Arc::new(Metal::new(
+                    Vec3::new(
+                        0.5 * (1.0 + random::<f32>()),
+                        0.5 * (1.0 + random::<f32>()),
+                        0.5 * (1.0 - random::<f32>()),
+                    ),
+                    0.5 * random::<f32>(),
+                ))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+
+            let obj_choice = random::<f32>();
+            if obj_choice < 0.3 {
+                world.push(Box::new(Sphere::new(center, radius, material)));
+            } else {
+                let center0 = center;
+                let center1 = center0 + Vec3::new(0.0, 1.5, 0.0) * random::<f32>();
+                world.push(Box::new(MovingSphere::new(
+                    center0, center1, 0.0, 1.0, radius, material,
+                )));
+            }
+        }
+    }
+    world.push(Box::new(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.push(Box::new(Sphere::new(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(Box::new(ConstantTexture::new(Vec3::new(
+            0.4, 0.2, 0.1,
+        ))))),
+    )));
+    world.push(Box::new(Sphere::new(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Metal::new(Vec3::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    Scene {
+        objects: world,
+        camera: CameraConfig {
+            lookfrom: Vec3::new(13.0, 2.0, 3.0),
+            lookat: Vec3::new(0.0, 0.0, 0.0),
+            vfov: 20.0,
+            ..CameraConfig::default()
+        },
+        environment: Box::new(Sky::default()),
+    }
+}
+
+fn simple_scene() -> Result<Scene, Box<dyn Error>> {
+    let img = load_texture()?;
+    let world: Vec<Box<dyn Hitable>> = vec![
+        Box::new(Plane::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Arc::new(Lambertian::new(Box::new(NoiseTexture::new(12.0)))),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            2.0,
+            Arc::new(Lambertian::new(Box::new(NoiseTexture::new(12.0)))),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(0.0, 1.0, 2.0),
+            1.0,
+            Arc::new(Lambertian::new(Box::new(ImageTexture::new(img)))),
+        )),
+    ];
+
+    Ok(Scene {
+        objects: world,
+        camera: CameraConfig {
+            lookfrom: Vec3::new(26.0, 3.0, 6.0),
+            lookat: Vec3::new(0.0, 2.0, 0.0),
+            vfov: 20.0,
+            ..CameraConfig::default()
+        },
+        environment: Box::new(Constant::new(Vec3::default())),
+    })
+}
+
+fn simple_light() -> Scene {
+    let world: Vec<Box<dyn Hitable>> = vec![
+        Box::new(Plane::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Arc::new(Lambertian::new(Box::new(NoiseTexture::new(12.0)))),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            2.0,
+            Arc::new(Lambertian::new(Box::new(NoiseTexture::new(12.0)))),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(0.0, 7.0, 0.0),
+            2.0,
+            Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+                Vec3::new(4.0, 4.0, 4.0),
+            )))),
+        )),
+        Box::new(XYRect::new(
+            3.0,
+            5.0,
+            1.0,
+            3.0,
+            -2.0,
+            Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+                Vec3::new(4.0, 4.0, 4.0),
+            )))),
+        )),
+    ];
+
+    Scene {
+        objects: world,
+        camera: CameraConfig {
+            lookfrom: Vec3::new(26.0, 3.0, 6.0),
+            lookat: Vec3::new(0.0, 2.0, 0.0),
+            vfov: 20.0,
+            ..CameraConfig::default()
+        },
+        environment: Box::new(Constant::new(Vec3::default())),
+    }
+}
+
+fn cornel_box() -> Scene {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.65, 0.05, 0.05),
+    ))));
+    let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.73, 0.73, 0.73),
+    ))));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(1.0, 1.0, 1.0),
+    ))));
+    let black: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.0, 0.0, 0.0),
+    ))));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.12, 0.45, 0.15),
+    ))));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+        Vec3::new(15.0, 15.0, 15.0),
+    ))));
+    let world: Vec<Box<dyn Hitable>> = vec![
+        Box::new(FlipNormal::new(Box::new(YZRect::new(
+            0.0,
+            555.0,
+            0.0,
+            555.0,
+            555.0,
+            Arc::clone(&green),
+        )))),
+        Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, Arc::clone(&red))),
+        Box::new(XZRect::new(
+            213.0,
+            343.0,
+            227.0,
+            332.0,
+            554.0,
+            Arc::clone(&light),
+        )),
+        Box::new(FlipNormal::new(Box::new(XZRect::new(
+            0.0,
+            555.0,
+            0.0,
+            555.0,
+            555.0,
+            Arc::clone(&gray),
+        )))),
+        Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, Arc::clone(&gray))),
+        Box::new(FlipNormal::new(Box::new(XYRect::new(
+            0.0,
+            555.0,
+            0.0,
+            555.0,
+            555.0,
+            Arc::clone(&gray),
+        )))),
+        Box::new(ConstantMedium::new(
+            0.01,
+            Arc::clone(&black),
+            Box::new(Translate::new(
+                Vec3::new(130.0, 0.0, 65.0),
+                Box::new(RotateY::new(
+                    -18.0,
+                    Box::new(Box_::new(
+                        Vec3::new(0.0, 0.0, 0.0),
+                        Vec3::new(165.0, 165.0, 165.0),
+                        Arc::clone(&white),
+                    )),
+                )),
+            )),
+        )),
+        Box::new(ConstantMedium::new(
+            0.01,
+            Arc::clone(&white),
+            Box::new(Translate::new(
+                Vec3::new(265.0, 0.0, 295.0),
+                Box::new(RotateY::new(
+                    15.0,
+                    Box::new(Box_::new(
+                        Vec3::new(0.0, 0.0, 0.0),
+                        Vec3::new(165.0, 330.0, 165.0),
+                        Arc::clone(&black),
+                    )),
+                )),
+            )),
+        )),
+    ];
+
+    Scene {
+        objects: world,
+        camera: CameraConfig {
+            lookfrom: Vec3::new(278.0, 278.0, -800.0),
+            lookat: Vec3::new(278.0, 278.0, 0.0),
+            vfov: 40.0,
+            ..CameraConfig::default()
+        },
+        environment: Box::new(Constant::new(Vec3::default())),
+    }
+}
+
+fn second_weekend_final() -> Result<Scene, Box<dyn Error>> {
+    let img = load_texture()?;
+    let ground: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.48, 0.83, 0.53),
+    ))));
+    let brown: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.7, 0.3, 0.1),
+    ))));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(
+        Vec3::new(0.73, 0.73, 0.73),
+    ))));
+    // One unit box and one group of spheres, built once and placed by
+    // instances in a top-level hierarchy.
+    let unit_box: Arc<dyn Hitable> = Arc::new(Box_::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 1.0),
+        Arc::clone(&ground),
+    ));
+    let mut instances = (0..400)
+        .map(|idx| {
+            let i = idx / 20;
+            let j = idx % 20;
+            let w = 100.0;
+            let x0 = -1000.0 + i as f32 * w;
+            let z0 = -1000.0 + j as f32 * w;
+            let y1 = 100.0 * (random::<f32>() + 0.01);
+            Transform::new(
+                Matrix4::translate(&Vec3::new(x0, 0.0, z0)) * Matrix4::scale(&Vec3::new(w, y1, w)),
+                Arc::clone(&unit_box),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let group_spheres: Arc<dyn Hitable> = Arc::new(
+        (0..1000)
+            .map(|_| {
+                let x = 165.0 * random::<f32>();
+                let y = 165.0 * random::<f32>();
+                let z = 165.0 * random::<f32>();
+                Box::new(Sphere::new(Vec3::new(x, y, z), 10.0, Arc::clone(&white)))
+                    as Box<dyn Hitable>
+            })
+            .collect::<HitableList>(),
+    );
+    instances.push(Transform::new(
+        Matrix4::translate(&Vec3::new(-100.0, 270.0, 395.0))
+            * Matrix4::rotate(&Vec3::new(0.0, 1.0, 0.0), 15.0),
+        group_spheres,
+    ));
+    let tlas = Tlas::new(instances, SplitStrategy::default());
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+        Vec3::new(7.0, 7.0, 7.0),
+    ))));
+    let center = Vec3::new(400.0, 400.0, 200.0);
+
+    let world: Vec<Box<dyn Hitable>> = vec![
+        Box::new(tlas),
+        Box::new(XZRect::new(
+            123.0,
+            423.0,
+            147.0,
+            412.0,
+            554.0,
+            Arc::clone(&light),
+        )),
+        Box::new(MovingSphere::new(
+            center,
+            center + Vec3::new(30.0, 0.0, 0.0),
+            0.0,
+            1.0,
+            50.0,
+            Arc::clone(&brown),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(260.0, 150.0, 45.0),
+            50.0,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(0.0, 150.0, 145.0),
+            50.0,
+            Arc::new(Metal::new(Vec3::new(0.8, 0.8, 0.9), 10.0)),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(360.0, 160.0, 145.0),
+            70.0,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(ConstantMedium::new(
+            0.2,
+            Arc::new(Lambertian::new(Box::new(ConstantTexture::new(Vec3::new(
+                0.2, 0.4, 0.9,
+            ))))),
+            Box::new(Sphere::new(
+                Vec3::new(360.0, 160.0, 145.0),
+                70.0,
+                Arc::new(Dielectric::new(1.5)),
+            )),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            5000.0,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(ConstantMedium::new(
+            0.0001,
+            Arc::new(Lambertian::new(Box::new(ConstantTexture::new(Vec3::new(
+                1.0, 1.0, 1.0,
+            ))))),
+            Box::new(Sphere::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                5000.0,
+                Arc::new(Dielectric::new(1.5)),
+            )),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(400.0, 200.0, 400.0),
+            100.0,
+            Arc::new(Lambertian::new(Box::new(ImageTexture::new(img)))),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(220.0, 200.0, 300.0),
+            80.0,
+            Arc::new(Lambertian::new(Box::new(NoiseTexture::new(0.1)))),
+        )),
+    ];
+    Ok(Scene {
+        objects: world,
+        camera: CameraConfig {
+            lookfrom: Vec3::new(428.0, 278.0, -800.0),
+            lookat: Vec3::new(278.0, 278.0, 0.0),
+            vfov: 40.0,
+            ..CameraConfig::default()
+        },
+        environment: Box::new(Constant::new(Vec3::default())),
+    })
+}
@@ -0,0 +1,422 @@
+use std::sync::Arc;
+
+use super::aabb::AABB;
+use super::hitable::{HitRecord, Hitable};
+use super::material::Material;
+use super::ray::Ray;
+use super::vec3::*;
+
+/// A signed distance function: negative inside the surface, positive
+/// outside, and never more than the distance to the surface.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: &Vec3) -> f32;
+}
+
+fn max_components(v: &Vec3, floor: f32) -> Vec3 {
+    Vec3::new(v.x().max(floor), v.y().max(floor), v.z().max(floor))
+}
+
+pub struct SphereSdf {
+    center: Vec3,
+    radius: f32,
+}
+
+impl SphereSdf {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Sdf for SphereSdf {
+    fn distance(&self, p: &Vec3) -> f32 {
+        (*p - self.center).lenght() - self.radius
+    }
+}
+
+/// A box of `half_extents` around `center`, its edges rounded off by
+/// `radius`.
+pub struct RoundBoxSdf {
+    center: Vec3,
+    half_extents: Vec3,
+    radius: f32,
+}
+
+impl RoundBoxSdf {
+    pub fn new(center: Vec3, half_extents: Vec3, radius: f32) -> Self {
+        Self {
+            center,
+            half_extents,
+            radius,
+        }
+    }
+}
+
+impl Sdf for RoundBoxSdf {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let d = *p - self.center;
+        let q = Vec3::new(d.x().abs(), d.y().abs(), d.z().abs()) - self.half_extents;
+        let outside = max_components(&q, 0.0).lenght();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - self.radius
+    }
+}
+
+/// A torus around `center`, lying in the xz plane.
+pub struct TorusSdf {
+    center: Vec3,
+    major_radius: f32,
+    minor_radius: f32,
+}
+
+impl TorusSdf {
+    pub fn new(center: Vec3, major_radius: f32, minor_radius: f32) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for TorusSdf {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let d = *p - self.center;
+        let ring = (d.x() * d.x() + d.z() * d.z()).sqrt() - self.major_radius;
+        (ring * ring + d.y() * d.y()).sqrt() - self.minor_radius
+    }
+}
+
+/// Every point within `radius` of the segment from `a` to `b`.
+pub struct CapsuleSdf {
+    a: Vec3,
+    b: Vec3,
+    radius: f32,
+}
+
+impl CapsuleSdf {
+    pub fn new(a: Vec3, b: Vec3, radius: f32) -> Self {
+        Self { a, b, radius }
+    }
+}
+
+impl Sdf for CapsuleSdf {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let pa = *p - self.a;
+        let ba = self.b - self.a;
+        let h = (dot(&pa, &ba) / dot(&ba, &ba)).clamp(0.0, 1.0);
+        (pa - h * ba).lenght() - self.radius
+    }
+}
+
+/// The Mandelbulb fractal of the given `power`, around the origin and
+/// within a radius of about 1.2 for the usual power of 8.
+///
+/// Its distance is an estimate from the escape-time iteration's running
+/// derivative, so marching needs more steps than for the exact shapes.
+pub struct Mandelbulb {
+    power: f32,
+    iterations: usize,
+}
+
+impl Mandelbulb {
+    pub fn new(power: f32, iterations: usize) -> Self {
+        Self { power, iterations }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let mut z = *p;
+        let mut dr = 1.0;
+        let mut r = z.lenght();
+        for _ in 0..self.iterations {
+            if 2.0 < r {
+                break;
+            }
+            // Raise z to `power` in spherical coordinates, then add p back.
+            let theta = (z.z() / r).acos() * self.power;
+            let phi = z.y().atan2(z.x()) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            let zr = r.powf(self.power);
+            z =
+                zr * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                ) + *p;
+            r = z.lenght();
+        }
+        0.5 * r.ln() * r / dr
+    }
+}
+
+/// Both shapes, blended together where they come within `k` of each other.
+pub struct SmoothUnion {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+    k: f32,
+}
+
+impl SmoothUnion {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>, k: f32) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        let h = (0.5 + 0.5 * (b - a) / self.k).clamp(0.0, 1.0);
+        b + (a - b) * h - self.k * h * (1.0 - h)
+    }
+}
+
+/// Shape `a` with `b` carved out of it, the edges of the cut rounded over
+/// `k`.
+pub struct SmoothSubtraction {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+    k: f32,
+}
+
+impl SmoothSubtraction {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>, k: f32) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothSubtraction {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        let h = (0.5 - 0.5 * (a + b) / self.k).clamp(0.0, 1.0);
+        a + (-b - a) * h + self.k * h * (1.0 - h)
+    }
+}
+
+/// Copies of a shape repeating every `period` along each axis, or not at
+/// all along axes where the period is zero.
+///
+/// The shape should fit within one period around the origin.
+pub struct Repeat {
+    period: Vec3,
+    sdf: Box<dyn Sdf>,
+}
+
+impl Repeat {
+    pub fn new(period: Vec3, sdf: Box<dyn Sdf>) -> Self {
+        Self { period, sdf }
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: &Vec3) -> f32 {
+        let fold = |x: f32, period: f32| {
+            if period == 0.0 {
+                x
+            } else {
+                x - period * (x / period).round()
+            }
+        };
+        let q = Vec3::new(
+            fold(p.x(), self.period.x()),
+            fold(p.y(), self.period.y()),
+            fold(p.z(), self.period.z()),
+        );
+        self.sdf.distance(&q)
+    }
+}
+
+const MAX_STEPS: usize = 512;
+const EPSILON: f32 = 1e-4;
+
+/// An implicit surface, found by sphere tracing its distance function
+/// within a given bounding box.
+///
+/// Normals come from the gradient of the distance. There is no natural
+/// parameterization, so texture coordinates are zero.
+pub struct SdfObject {
+    sdf: Box<dyn Sdf>,
+    bbox: AABB,
+    material: Arc<dyn Material>,
+}
+
+impl SdfObject {
+    pub fn new(sdf: Box<dyn Sdf>, bbox: AABB, material: Arc<dyn Material>) -> Self {
+        Self {
+            sdf,
+            bbox,
+            material,
+        }
+    }
+
+    /// Gradient of the distance by differences along the corners of a
+    /// tetrahedron, which takes four lookups instead of six.
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        let h = EPSILON;
+        let gradient = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Vec3::default(), |sum, k| {
+            sum + self.sdf.distance(&(*p + h * *k)) * *k
+        });
+        unit_vector(&gradient)
+    }
+}
+
+impl Hitable for SdfObject {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t_start, t_end) = self.bbox.hit(r, t_min, t_max)?;
+        // March in units of distance along the ray.
+        let len = r.direction().lenght();
+        let dir = *r.direction() / len;
+        let (mut s, s_end) = (t_start * len, t_end * len);
+        let at = |s: f32| *r.origin() + s * dir;
+
+        // Rays leaving the surface, as a reflected or refracted one does,
+        // must first get clear of it before they can hit it again. Whether
+        // they travel outside or inside is then up to the gradient.
+        let d0 = self.sdf.distance(&at(s));
+        let mut clear = EPSILON <= d0.abs();
+        let sign = if clear {
+            d0.signum()
+        } else if 0.0 < dot(&self.normal(&at(s)), &dir) {
+            1.0
+        } else {
+            -1.0
+        };
+        for _ in 0..MAX_STEPS {
+            if s_end < s {
+                return None;
+            }
+            let d = sign * self.sdf.distance(&at(s));
+            if clear && d < EPSILON {
+                let t = s / len;
+                let p = r.point_at_parameter(t);
+                return Some(HitRecord {
+                    t,
+                    p,
+                    u: 0.0,
+                    v: 0.0,
+                    normal: self.normal(&p),
+                    material: Arc::clone(&self.material),
+                });
+            }
+            clear |= EPSILON <= d;
+            s += d.max(EPSILON);
+        }
+        None
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        self.bbox.clone()
+    }
+}
+
+#[test]
+fn test_sdf() {
+    use super::material::Dielectric;
+    use super::sphere::Sphere;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let unit_box = |r: f32| AABB::new(Vec3::new(-r, -r, -r), Vec3::new(r, r, r));
+
+    // Tracing a sphere's distance agrees with solving for the sphere.
+    let center = Vec3::new(0.2, -0.1, 0.3);
+    let traced = SdfObject::new(
+        Box::new(SphereSdf::new(center, 1.0)),
+        unit_box(1.5),
+        Arc::clone(&glass),
+    );
+    let exact = Sphere::new(center, 1.0, Arc::clone(&glass));
+    let mut rng = StdRng::seed_from_u64(6);
+    for _ in 0..200 {
+        let o = Vec3::new(
+            rng.gen_range(-4.0..4.0),
+            rng.gen_range(-4.0..4.0),
+            rng.gen_range(-4.0..4.0),
+        );
+        let target = Vec3::new(
+            rng.gen_range(-1.2..1.2),
+            rng.gen_range(-1.2..1.2),
+            rng.gen_range(-1.2..1.2),
+        );
+        let r = Ray::new(o, target - o, 0.0);
+        match (
+            traced.hit(&r, 0.001, f32::MAX),
+            exact.hit(&r, 0.001, f32::MAX),
+        ) {
+            (Some(a), Some(b)) => {
+                assert!((a.t - b.t).abs() < 1e-3, "{} vs {}", a.t, b.t);
+                assert!(0.999 < dot(&a.normal, &b.normal));
+                // A refracted ray carries on from the surface to the far side.
+                let inward = Ray::new(a.p, center - a.p, 0.0);
+                let exit = traced.hit(&inward, 0.001, f32::MAX).unwrap();
+                assert!((exit.t - 2.0 / (center - a.p).lenght()).abs() < 1e-3);
+                assert!(0.0 < dot(&exit.normal, inward.direction()));
+            }
+            (None, None) => {}
+            (a, b) => {
+                // Only rays grazing the sphere may disagree.
+                let grazing = |rec: HitRecord| dot(&rec.normal, &unit_vector(&(target - o))).abs();
+                let cosine = a.or(b).map(grazing).unwrap();
+                assert!(cosine < 0.05, "cosine {}", cosine);
+            }
+        }
+    }
+
+    // Each shape's distance is exact at a few known points.
+    let round_box = RoundBoxSdf::new(Vec3::default(), Vec3::new(1.0, 2.0, 3.0), 0.5);
+    assert!((round_box.distance(&Vec3::new(3.0, 0.0, 0.0)) - 1.5).abs() < 1e-6);
+    assert!((round_box.distance(&Vec3::default()) + 1.5).abs() < 1e-6);
+    let corner = Vec3::new(2.0, 3.0, 3.0);
+    assert!((round_box.distance(&corner) - (2f32.sqrt() - 0.5)).abs() < 1e-6);
+    let torus = TorusSdf::new(Vec3::default(), 2.0, 0.5);
+    assert!((torus.distance(&Vec3::new(0.0, 1.0, 2.0)) - 0.5).abs() < 1e-6);
+    let capsule = CapsuleSdf::new(Vec3::default(), Vec3::new(0.0, 2.0, 0.0), 0.5);
+    assert!((capsule.distance(&Vec3::new(0.0, 3.0, 0.0)) - 0.5).abs() < 1e-6);
+    assert!((capsule.distance(&Vec3::new(1.0, 1.0, 0.0)) - 0.5).abs() < 1e-6);
+
+    // Blending two spheres fills in the gap between them, and carving one
+    // out of another rounds off the cut; away from where they meet, they
+    // are left alone.
+    let sphere = |x: f32| Box::new(SphereSdf::new(Vec3::new(x, 0.0, 0.0), 0.9));
+    let blend = SmoothUnion::new(sphere(-1.0), sphere(1.0), 0.5);
+    assert!(blend.distance(&Vec3::default()) < 0.0);
+    assert!((blend.distance(&Vec3::new(-3.0, 0.0, 0.0)) - 1.1).abs() < 1e-6);
+    let bitten = SmoothSubtraction::new(sphere(-1.0), sphere(0.5), 0.5);
+    assert!(0.0 < bitten.distance(&Vec3::new(-0.3, 0.0, 0.0)));
+    assert!((bitten.distance(&Vec3::new(-1.8, 0.0, 0.0)) + 0.1).abs() < 1e-6);
+
+    // Repeated spheres along x and z, but not y.
+    let grid = Repeat::new(
+        Vec3::new(3.0, 0.0, 3.0),
+        Box::new(SphereSdf::new(Vec3::default(), 1.0)),
+    );
+    assert!((grid.distance(&Vec3::new(30.0, 2.0, -6.0)) - 1.0).abs() < 1e-5);
+    assert!((grid.distance(&Vec3::new(0.0, 12.0, 0.0)) - 11.0).abs() < 1e-5);
+
+    // Rays towards the Mandelbulb stop on its surface, within its radius,
+    // and see it facing them away from its poles, where the estimate is
+    // too rough for a gradient.
+    let mandelbulb = Mandelbulb::new(8.0, 12);
+    let bulb = SdfObject::new(Box::new(Mandelbulb::new(8.0, 12)), unit_box(1.5), glass);
+    for o in [
+        Vec3::new(3.0, 0.5, 0.3),
+        Vec3::new(-3.0, 1.0, 0.5),
+        Vec3::new(1.0, -3.0, 1.0),
+        Vec3::new(2.0, 2.0, 1.0),
+    ]
+    .iter()
+    {
+        let r = Ray::new(*o, -*o, 0.0);
+        let rec = bulb.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!(mandelbulb.distance(&rec.p).abs() < 1e-3 && rec.p.lenght() < 1.2);
+        assert!(dot(&rec.normal, r.direction()) < 0.0);
+    }
+}
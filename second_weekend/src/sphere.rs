@@ -1,10 +1,12 @@
 use std::f64::consts::PI;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::aabb::AABB;
-use super::hitable::{HitRecord, Hitable};
+use super::hitable::{Crossing, HitRecord, Hitable};
 use super::material::Material;
+use super::onb::Onb;
 use super::ray::Ray;
+use super::sampler::Sampler;
 use super::vec3::*;
 
 fn get_sphere_uv(p: &Vec3, center: &Vec3) -> (f32, f32) {
@@ -20,14 +22,33 @@ fn get_sphere_uv(p: &Vec3, center: &Vec3) -> (f32, f32) {
     (u, v)
 }
 
+/// Cosine of the half angle of the cone subtended by a sphere of `radius`
+/// at `distance_squared`. From inside or on the sphere every direction
+/// leads to it, so the cone opens up to the whole sphere of directions.
+fn cos_theta_max(radius: f32, distance_squared: f32) -> f32 {
+    if distance_squared <= radius * radius {
+        -1.0
+    } else {
+        (1.0 - radius * radius / distance_squared).sqrt()
+    }
+}
+
+/// Uniform direction within the cone of `cos_theta_max` around the z axis.
+fn random_to_sphere(cos_theta_max: f32, (r1, r2): (f32, f32)) -> Vec3 {
+    let z = 1.0 + r2 * (cos_theta_max - 1.0);
+    let phi = 2.0 * PI as f32 * r1;
+    let sin_theta = (1.0 - z * z).sqrt();
+    Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+}
+
 pub struct Sphere {
     center: Vec3,
     radius: f32,
-    material: Rc<dyn Material>,
+    material: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32, material: Rc<dyn Material>) -> Self {
+    pub fn new(center: Vec3, radius: f32, material: Arc<dyn Material>) -> Self {
         Self {
             center,
             radius,
@@ -36,51 +57,124 @@ impl Sphere {
     }
 }
 
-impl Hitable for Sphere {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+impl Sphere {
+    /// Where the line through `r` enters and leaves the sphere.
+    fn roots(&self, r: &Ray) -> Option<(f32, f32)> {
         let oc = *r.origin() - self.center;
         let a = dot(r.direction(), r.direction());
         let b = 2.0 * dot(&oc, r.direction());
         let c = dot(&oc, &oc) - self.radius * self.radius;
         let discriminant = b * b - 4.0 * a * c;
-        if 0.0 < discriminant {
-            let near_t = -(b + discriminant.sqrt()) / (2.0 * a);
-            if (t_min < near_t) && (near_t < t_max) {
-                let t = near_t;
-                let p = r.point_at_parameter(t);
-                let normal = (p - self.center) / self.radius;
-                let (u, v) = get_sphere_uv(&p, &self.center);
-                return Some(HitRecord {
-                    t,
-                    p,
-                    u,
-                    v,
-                    normal,
-                    material: Rc::clone(&self.material),
-                });
-            }
-
-            let far_t = -(b - discriminant.sqrt()) / (2.0 * a);
-            if (t_min < far_t) && (far_t < t_max) {
-                let t = far_t;
-                let p = r.point_at_parameter(t);
-                let normal = (p - self.center) / self.radius;
-                let (u, v) = get_sphere_uv(&p, &self.center);
-                return Some(HitRecord {
-                    t,
-                    p,
-                    u,
-                    v,
-                    normal,
-                    material: Rc::clone(&self.material),
-                });
-            }
+        if discriminant <= 0.0 {
+            return None;
         }
-        None
+        Some((
+            -(b + discriminant.sqrt()) / (2.0 * a),
+            -(b - discriminant.sqrt()) / (2.0 * a),
+        ))
+    }
+
+    fn record(&self, r: &Ray, t: f32) -> HitRecord {
+        let p = r.point_at_parameter(t);
+        let normal = (p - self.center) / self.radius;
+        let (u, v) = get_sphere_uv(&p, &self.center);
+        HitRecord {
+            t,
+            p,
+            u,
+            v,
+            normal,
+            material: Arc::clone(&self.material),
+        }
+    }
+}
+
+impl Hitable for Sphere {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (near_t, far_t) = self.roots(r)?;
+        [near_t, far_t]
+            .iter()
+            .copied()
+            .find(|&t| t_min < t && t < t_max)
+            .map(|t| self.record(r, t))
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
         let radius_vec = Vec3::new(self.radius, self.radius, self.radius);
         AABB::new(self.center - radius_vec, self.center + radius_vec)
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        if self.hit(&Ray::new(*o, *v, 0.0), 0.001, f32::MAX).is_none() {
+            return 0.0;
+        }
+        let distance_squared = (self.center - *o).squared_length();
+        let cos_theta_max = cos_theta_max(self.radius, distance_squared);
+        let solid_angle = 2.0 * PI as f32 * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self.center - *o;
+        let cos_theta_max = cos_theta_max(self.radius, direction.squared_length());
+        // Around any axis at all when the cone covers every direction.
+        let axis = if cos_theta_max < 0.0 {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            direction
+        };
+        Onb::from_w(&axis).local(&random_to_sphere(cos_theta_max, sampler.next_2d()))
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        match self.roots(r) {
+            Some((near_t, far_t)) => vec![
+                Crossing {
+                    hit: self.record(r, near_t),
+                    entering: true,
+                },
+                Crossing {
+                    hit: self.record(r, far_t),
+                    entering: false,
+                },
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn test_sphere_light_from_inside() {
+    use super::material::DiffuseLight;
+    use super::sampler::Independent;
+    use super::texture::ConstantTexture;
+
+    let light = Sphere::new(
+        Vec3::default(),
+        2.0,
+        Arc::new(DiffuseLight::new(Box::new(ConstantTexture::new(
+            Vec3::new(1.0, 1.0, 1.0),
+        )))),
+    );
+    let mut sampler = Independent;
+    // From inside, and from the center itself, directions are uniform.
+    for o in [Vec3::new(0.5, 0.0, 0.0), Vec3::default()].iter() {
+        let v = light.random(o, &mut sampler);
+        assert!(v.lenght().is_finite());
+        let pdf = light.pdf_value(o, &v);
+        assert!((pdf - 1.0 / (4.0 * PI as f32)).abs() < 1e-6, "{}", pdf);
+    }
+    // From outside, within the cone of half angle 30 degrees.
+    let o = Vec3::new(0.0, 0.0, 4.0);
+    let v = light.random(&o, &mut sampler);
+    let solid_angle = 2.0 * PI as f32 * (1.0 - 0.75f32.sqrt());
+    assert!((light.pdf_value(&o, &v) - 1.0 / solid_angle).abs() < 1e-3);
 }
@@ -3,7 +3,7 @@ use image::{Rgb, RgbImage};
 use super::perlin::Perlin;
 use super::vec3::Vec3;
 
-pub trait Texture {
+pub trait Texture: Send + Sync {
     fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3;
 }
 
@@ -52,7 +52,7 @@ pub struct NoiseTexture {
 impl NoiseTexture {
     pub fn new(scale: f32) -> Self {
         Self {
-            noise: Perlin::default(),
+            noise: Perlin::new(),
             scale,
         }
     }
@@ -76,8 +76,8 @@ impl ImageTexture {
 
 impl Texture for ImageTexture {
     fn value(&self, u: f32, v: f32, _p: &Vec3) -> Vec3 {
-        let i = (u.max(0.0).min(1.0) * (self.data.width() - 1) as f32).floor() as u32;
-        let j = ((1.0 - v).max(0.0).min(1.0) * (self.data.height() - 1) as f32).floor() as u32;
+        let i = (u.clamp(0.0, 1.0) * (self.data.width() - 1) as f32).floor() as u32;
+        let j = ((1.0 - v).clamp(0.0, 1.0) * (self.data.height() - 1) as f32).floor() as u32;
         let Rgb([r, g, b]) = self.data.get_pixel(i, j);
         Vec3::new(*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0)
     }
@@ -0,0 +1,143 @@
+use super::aabb::AABB;
+use super::hitable::{HitRecord, Hitable};
+use super::hitable_list::{Bvh, LightList, SplitStrategy};
+use super::ray::Ray;
+use super::sampler::{Independent, Sampler};
+#[cfg(test)]
+use super::transform::Matrix4;
+use super::transform::Transform;
+use super::vec3::Vec3;
+
+fn boxes(instances: &[Transform]) -> Vec<AABB> {
+    instances
+        .iter()
+        .map(|instance| instance.bounding_box(0.0, 1.0))
+        .collect()
+}
+
+/// Top level of a two-level acceleration structure.
+///
+/// Each instance places a shared bottom-level structure, such as a mesh or
+/// a `HitableList` built once, through its own transform. Only the
+/// instances' boxes go into this hierarchy, so moving instances never
+/// touches the geometry underneath.
+pub struct Tlas {
+    instances: Vec<Transform>,
+    bvh: Bvh,
+    lights: LightList,
+}
+
+impl Tlas {
+    /// Panics if there are no instances.
+    pub fn new(instances: Vec<Transform>, strategy: SplitStrategy) -> Self {
+        Self {
+            bvh: Bvh::new(&boxes(&instances), strategy),
+            lights: LightList::new(&instances),
+            instances,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Tlas {
+    /// Moves the instances to `matrices`, one for each instance in the order
+    /// they were given, and refits the hierarchy around them.
+    pub fn set_transforms(&mut self, matrices: &[Matrix4]) {
+        assert_eq!(matrices.len(), self.instances.len());
+        for (instance, &matrix) in self.instances.iter_mut().zip(matrices) {
+            *instance = instance.moved(matrix);
+        }
+        self.bvh.refit(&boxes(&self.instances));
+    }
+}
+
+impl Hitable for Tlas {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.bvh.hit(r, t_min, t_max, |idx, t_max| {
+            self.instances[idx].hit_sampled(r, t_min, t_max, sampler)
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        self.bvh.bounding_box()
+    }
+
+    fn is_light(&self) -> bool {
+        !self.lights.is_empty()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.lights.pdf_value(&self.instances, o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.lights.random(&self.instances, o, sampler)
+    }
+}
+
+#[test]
+fn test_tlas_over_moved_instances() {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::Arc;
+
+    use super::material::Dielectric;
+    use super::sphere::Sphere;
+
+    let mut rng = StdRng::seed_from_u64(11);
+    let sphere: Arc<dyn Hitable> = Arc::new(Sphere::new(
+        Vec3::default(),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    let placement = |rng: &mut StdRng| {
+        Matrix4::translate(&Vec3::new(
+            rng.gen_range(-10.0..10.0),
+            rng.gen_range(-10.0..10.0),
+            rng.gen_range(-1.0..1.0),
+        )) * Matrix4::scale(&Vec3::new(rng.gen_range(0.2..1.0), 0.5, 0.5))
+    };
+    let mut matrices = (0..100).map(|_| placement(&mut rng)).collect::<Vec<_>>();
+    let instances = matrices
+        .iter()
+        .map(|&m| Transform::new(m, Arc::clone(&sphere)))
+        .collect();
+    let mut tlas = Tlas::new(instances, SplitStrategy::Sah);
+    assert_eq!(Arc::strong_count(&sphere), 101);
+
+    let check = |tlas: &Tlas, matrices: &[Matrix4], rng: &mut StdRng| {
+        for _ in 0..300 {
+            let origin = Vec3::new(rng.gen_range(-12.0..12.0), rng.gen_range(-12.0..12.0), 5.0);
+            let target = Vec3::new(rng.gen_range(-12.0..12.0), rng.gen_range(-12.0..12.0), 0.0);
+            let r = Ray::new(origin, target - origin, 0.0);
+            let expected = matrices
+                .iter()
+                .filter_map(|&m| Transform::new(m, Arc::clone(&sphere)).hit(&r, 0.001, f32::MAX))
+                .map(|rec| rec.t)
+                .min_by(f32::total_cmp);
+            assert_eq!(tlas.hit(&r, 0.001, f32::MAX).map(|rec| rec.t), expected);
+        }
+    };
+    check(&tlas, &matrices, &mut rng);
+
+    // Move half of the instances, as between two frames, sharing the same
+    // sphere throughout. One of them leaves the box the others stay in.
+    for idx in (0..matrices.len()).step_by(2) {
+        matrices[idx] = placement(&mut rng);
+    }
+    matrices[0] = Matrix4::translate(&Vec3::new(0.0, 30.0, 0.0));
+    tlas.set_transforms(&matrices);
+    check(&tlas, &matrices, &mut rng);
+    assert!(30.0 < tlas.bounding_box(0.0, 1.0).max.y());
+    assert_eq!(Arc::strong_count(&sphere), 101);
+}
@@ -0,0 +1,148 @@
+use super::vec3::Vec3;
+
+/// How radiance is squeezed into the displayable range.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMapOperator {
+    /// Cuts off everything brighter than white.
+    #[default]
+    Clamp,
+    /// Compresses luminance as `l / (1 + l)`.
+    Reinhard,
+    /// Reinhard, reaching white at the given white point.
+    ReinhardExtended,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+fn luminance(c: &Vec3) -> f32 {
+    0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b()
+}
+
+/// Scales `c` so that its luminance becomes `f` of what it was.
+fn map_luminance(c: &Vec3, f: impl Fn(f32) -> f32) -> Vec3 {
+    let l = luminance(c);
+    if l <= 0.0 {
+        return Vec3::default();
+    }
+    f(l) / l * *c
+}
+
+fn aces(x: f32) -> f32 {
+    // The fit expects radiance pre-exposed for the ACES reference white.
+    let x = 0.6 * x;
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+/// sRGB transfer curve, from linear light to encoded values.
+fn srgb_encode(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Turns linear radiance into display values: an exposure in stops, a tone
+/// mapping operator and the sRGB curve.
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapping {
+    pub exposure: f32,
+    pub operator: ToneMapOperator,
+    /// Radiance that maps to white with `ReinhardExtended`.
+    pub white: f32,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            operator: ToneMapOperator::default(),
+            white: 4.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Display-referred linear color, in [0, 1].
+    pub fn map(&self, radiance: &Vec3) -> Vec3 {
+        let c = 2f32.powf(self.exposure)
+            * Vec3::new(
+                radiance.x().max(0.0),
+                radiance.y().max(0.0),
+                radiance.z().max(0.0),
+            );
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => c,
+            ToneMapOperator::Reinhard => map_luminance(&c, |l| l / (1.0 + l)),
+            ToneMapOperator::ReinhardExtended => {
+                let white2 = self.white * self.white;
+                map_luminance(&c, |l| l * (1.0 + l / white2) / (1.0 + l))
+            }
+            ToneMapOperator::Aces => Vec3::new(aces(c.x()), aces(c.y()), aces(c.z())),
+        };
+        Vec3::new(
+            mapped.x().clamp(0.0, 1.0),
+            mapped.y().clamp(0.0, 1.0),
+            mapped.z().clamp(0.0, 1.0),
+        )
+    }
+
+    /// 8-bit sRGB encoding of `radiance`.
+    pub fn encode_srgb8(&self, radiance: &Vec3) -> [u8; 3] {
+        let c = self.map(radiance);
+        let encode = |c: f32| (255.0 * srgb_encode(c) + 0.5) as u8;
+        [encode(c.r()), encode(c.g()), encode(c.b())]
+    }
+}
+
+#[test]
+fn test_tone_mapping() {
+    let tm = |operator| ToneMapping {
+        operator,
+        ..ToneMapping::default()
+    };
+    let gray = Vec3::new(0.18, 0.18, 0.18);
+    let lamp = Vec3::new(4.0, 2.0, 1.0);
+
+    // Middle gray encodes to the familiar sRGB value.
+    assert_eq!(
+        tm(ToneMapOperator::Clamp).encode_srgb8(&gray),
+        [118, 118, 118]
+    );
+    assert_eq!(
+        tm(ToneMapOperator::Clamp).encode_srgb8(&lamp),
+        [255, 255, 255]
+    );
+    assert_eq!(
+        ToneMapping::default().encode_srgb8(&Vec3::default()),
+        [0, 0, 0]
+    );
+
+    // One stop up doubles linear light.
+    let brighter = ToneMapping {
+        exposure: 1.0,
+        ..ToneMapping::default()
+    };
+    assert!((brighter.map(&gray).x() - 0.36).abs() < 1e-6);
+
+    // The other operators keep the hue of bright lights instead of clipping
+    // them to white, and never go past white.
+    for operator in [
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::ReinhardExtended,
+        ToneMapOperator::Aces,
+    ] {
+        let c = tm(operator).map(&lamp);
+        assert!(
+            c.r() <= 1.0 && c.b() < c.g() && c.g() < c.r(),
+            "{:?}",
+            operator
+        );
+        let g = tm(operator).map(&gray).x();
+        assert!(0.0 < g && g < 0.18 * 1.5, "{:?}: {}", operator, g);
+    }
+
+    // The extended operator reaches white exactly at its white point.
+    let white = tm(ToneMapOperator::ReinhardExtended).map(&Vec3::new(4.0, 4.0, 4.0));
+    assert!((white.x() - 1.0).abs() < 1e-6);
+}
@@ -0,0 +1,310 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use super::aabb::AABB;
+use super::hitable::{Crossing, HitRecord, Hitable};
+use super::material::Material;
+use super::onb::Onb;
+use super::plane::azimuth;
+use super::ray::Ray;
+use super::vec3::*;
+
+const EPS: f64 = 1e-9;
+
+/// Real roots of `x^2 + b x + c` where it changes sign. A double root only
+/// touches zero and is left out.
+fn solve_quadratic(b: f64, c: f64) -> Vec<f64> {
+    let p = 0.5 * b;
+    let d = p * p - c;
+    if d < EPS {
+        vec![]
+    } else {
+        let s = d.sqrt();
+        vec![-p - s, -p + s]
+    }
+}
+
+/// Real roots of `x^3 + a x^2 + b x + c` where it changes sign, by
+/// Cardano's formula. A double root only touches zero and is left out.
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substituting x = y - a / 3 leaves y^3 + 3 p y + 2 q.
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = 0.5 * (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c);
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let roots = if d.abs() < EPS {
+        if q.abs() < EPS {
+            vec![0.0]
+        } else {
+            vec![2.0 * (-q).cbrt()]
+        }
+    } else if d < 0.0 {
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let s = d.sqrt();
+        vec![(s - q).cbrt() - (s + q).cbrt()]
+    };
+    roots.into_iter().map(|y| y - a / 3.0).collect()
+}
+
+/// Real roots of `x^4 + a x^3 + b x^2 + c x + d`, by Ferrari's method,
+/// each polished with a few Newton steps.
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Substituting x = y - a / 4 leaves y^4 + p y^2 + q y + r.
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = if r.abs() < EPS {
+        let mut roots = solve_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // Any real root of the resolvent cubic splits the quartic into two
+        // quadratics.
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if u.abs() < EPS {
+            0.0
+        } else if 0.0 < u {
+            u.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if v.abs() < EPS {
+            0.0
+        } else if 0.0 < v {
+            v.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if q < 0.0 { -v } else { v };
+        let mut roots = solve_quadratic(v, z - u);
+        roots.extend(solve_quadratic(-v, z + u));
+        roots
+    };
+
+    let f = |x: f64| (((x + a) * x + b) * x + c) * x + d;
+    let df = |x: f64| ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+    for x in roots.iter_mut() {
+        *x -= a / 4.0;
+        for _ in 0..2 {
+            let slope = df(*x);
+            if slope.abs() < EPS {
+                break;
+            }
+            *x -= f(*x) / slope;
+        }
+    }
+    roots
+}
+
+/// A ring around `center`, its tube of `minor_radius` running at
+/// `major_radius` from `axis`.
+///
+/// `u` goes around the axis and `v` around the tube.
+pub struct Torus {
+    center: Vec3,
+    frame: Onb,
+    major_radius: f32,
+    minor_radius: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        debug_assert!(0.0 < axis.squared_length(), "torus axis is zero");
+        debug_assert!(
+            0.0 <= major_radius && 0.0 <= minor_radius,
+            "torus radii are negative"
+        );
+        Self {
+            center,
+            frame: Onb::from_w(&axis),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+}
+
+impl Torus {
+    /// Every `t` where the line through `r` crosses the surface, nearest
+    /// first.
+    fn roots(&self, r: &Ray) -> Vec<f32> {
+        let big_r = self.major_radius as f64;
+        let small_r = self.minor_radius as f64;
+        let o = self.frame.coords(&(*r.origin() - self.center));
+        let d = self.frame.coords(r.direction());
+        let len = d.lenght() as f64;
+        let (ox, oy, oz) = (o.x() as f64, o.y() as f64, o.z() as f64);
+        let (dx, dy, dz) = (d.x() as f64 / len, d.y() as f64 / len, d.z() as f64 / len);
+
+        // Skip ahead to the bounding sphere, so that the quartic is solved
+        // close to its roots where it is best conditioned.
+        let bound = big_r + small_r;
+        let n = ox * dx + oy * dy + oz * dz;
+        let c = ox * ox + oy * oy + oz * oz - bound * bound;
+        if n * n - c < 0.0 {
+            return Vec::new();
+        }
+        let start = -n - (n * n - c).sqrt();
+        let (ox, oy, oz) = (ox + start * dx, oy + start * dy, oz + start * dz);
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2) along p = o + s d.
+        let n = ox * dx + oy * dy + oz * dz;
+        let m = ox * ox + oy * oy + oz * oz + big_r * big_r - small_r * small_r;
+        let four_r2 = 4.0 * big_r * big_r;
+        let mut roots = solve_quartic(
+            4.0 * n,
+            4.0 * n * n + 2.0 * m - four_r2 * (dx * dx + dy * dy),
+            4.0 * n * m - 2.0 * four_r2 * (ox * dx + oy * dy),
+            m * m - four_r2 * (ox * ox + oy * oy),
+        );
+        roots.sort_by(f64::total_cmp);
+
+        // Where the line grazes the tube, the two factors of the quartic can
+        // each find the same root. Such a pair touches without crossing.
+        let mut crossing: Vec<f64> = Vec::with_capacity(roots.len());
+        for s in roots {
+            match crossing.last() {
+                Some(&last) if s - last < 1e-4 * small_r => {
+                    crossing.pop();
+                }
+                _ => crossing.push(s),
+            }
+        }
+        crossing
+            .into_iter()
+            .map(|s| ((start + s) / len) as f32)
+            .collect()
+    }
+
+    fn record(&self, r: &Ray, t: f32) -> HitRecord {
+        let p = r.point_at_parameter(t);
+        let local = self.frame.coords(&(p - self.center));
+        // Away from the circle running through the middle of the tube.
+        let ring = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        let core = Vec3::new(local.x(), local.y(), 0.0) * (self.major_radius / ring);
+        let outward = local - core;
+        let tube_angle =
+            (outward.z().atan2(ring - self.major_radius) as f64 / (2.0 * PI)).rem_euclid(1.0);
+        HitRecord {
+            t,
+            p,
+            u: azimuth(&local),
+            v: tube_angle as f32,
+            normal: unit_vector(&self.frame.local(&outward)),
+            material: Arc::clone(&self.material),
+        }
+    }
+}
+
+impl Hitable for Torus {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.roots(r)
+            .into_iter()
+            .find(|&t| t_min < t && t < t_max)
+            .map(|t| self.record(r, t))
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        self.roots(r)
+            .into_iter()
+            .map(|t| {
+                let hit = self.record(r, t);
+                let entering = dot(&hit.normal, r.direction()) < 0.0;
+                Crossing { hit, entering }
+            })
+            .collect()
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        let n = self.frame.w();
+        let extent = |a: f32| self.major_radius * (1.0 - a * a).max(0.0).sqrt() + self.minor_radius;
+        let e = Vec3::new(extent(n.x()), extent(n.y()), extent(n.z()));
+        AABB::new(self.center - e, self.center + e)
+    }
+}
+
+#[test]
+fn test_torus() {
+    use super::material::Dielectric;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Roots of (x - 1)(x + 2)(x - 3)(x - 0.5).
+    let mut roots = solve_quartic(-2.5, -4.0, 8.5, -3.0);
+    roots.sort_by(f64::total_cmp);
+    let expected = [-2.0, 0.5, 1.0, 3.0];
+    assert_eq!(roots.len(), 4);
+    for (x, e) in roots.iter().zip(expected) {
+        assert!((x - e).abs() < 1e-9, "{:?}", roots);
+    }
+
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    // Lying flat, like a ring on a table.
+    let torus = Torus::new(Vec3::default(), Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5, glass);
+    let r = Ray::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(-2.0, 0.0, 0.0), 0.0);
+    let rec = torus.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 3.75).abs() < 1e-5 && (rec.normal.x() - 1.0).abs() < 1e-5);
+    // Straight down through the hole misses, down onto the tube hits its top.
+    let hole = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    assert!(torus.hit(&hole, 0.001, f32::MAX).is_none());
+    let top = Ray::new(Vec3::new(0.0, 5.0, -2.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = torus.hit(&top, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.5).abs() < 1e-4 && (rec.normal.y() - 1.0).abs() < 1e-4);
+    assert!((rec.v - 0.25).abs() < 1e-4, "{}", rec.v);
+
+    // Random rays agree with marching along them in small steps, and their
+    // hits lie on the surface.
+    let mut rng = StdRng::seed_from_u64(3);
+    let inside = |p: &Vec3| {
+        let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - 2.0;
+        ring * ring + p.y() * p.y() < 0.25
+    };
+    for _ in 0..200 {
+        let o = Vec3::new(
+            rng.gen_range(-4.0..4.0),
+            rng.gen_range(-2.0..2.0),
+            rng.gen_range(-4.0..4.0),
+        );
+        if inside(&o) {
+            continue;
+        }
+        let target = Vec3::new(
+            rng.gen_range(-2.5..2.5),
+            rng.gen_range(-0.5..0.5),
+            rng.gen_range(-2.5..2.5),
+        );
+        let r = Ray::new(o, target - o, 0.0);
+        let marched = (1..4000)
+            .map(|i| i as f32 * 0.002)
+            .find(|&t| inside(&r.point_at_parameter(t)));
+        match (torus.hit(&r, 0.001, f32::MAX), marched) {
+            (Some(rec), Some(t)) => assert!((rec.t - t).abs() < 0.01, "{} vs {}", rec.t, t),
+            (None, None) => {}
+            (hit, t) => panic!("{:?} vs {:?}", hit.map(|rec| rec.t), t),
+        }
+    }
+}
@@ -0,0 +1,719 @@
+use std::ops;
+use std::sync::Arc;
+
+use super::aabb::{surrounding_box, AABB};
+use super::hitable::{Crossing, HitRecord, Hitable};
+use super::ray::Ray;
+use super::sampler::{Independent, Sampler};
+use super::vec3::*;
+
+/// Row-major 4x4 matrix acting on column vectors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self { m }
+    }
+
+    pub fn translate(offset: &Vec3) -> Self {
+        let mut t = Self::identity();
+        for i in 0..3 {
+            t.m[i][3] = offset[i];
+        }
+        t
+    }
+
+    pub fn scale(factors: &Vec3) -> Self {
+        let mut s = Self::identity();
+        for i in 0..3 {
+            s.m[i][i] = factors[i];
+        }
+        s
+    }
+
+    /// Rotation by `angle` degrees counterclockwise around `axis`.
+    pub fn rotate(axis: &Vec3, angle: f32) -> Self {
+        let a = unit_vector(axis);
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let t = 1.0 - cos;
+        Self {
+            m: [
+                [
+                    t * x * x + cos,
+                    t * x * y - sin * z,
+                    t * x * z + sin * y,
+                    0.0,
+                ],
+                [
+                    t * x * y + sin * z,
+                    t * y * y + cos,
+                    t * y * z - sin * x,
+                    0.0,
+                ],
+                [
+                    t * x * z - sin * y,
+                    t * y * z + sin * x,
+                    t * z * z + cos,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = Self::identity();
+        for (i, row) in t.m.iter_mut().enumerate() {
+            for (j, e) in row.iter_mut().enumerate() {
+                *e = self.m[j][i];
+            }
+        }
+        t
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting, `None` if the matrix
+    /// is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let p = a[col][col];
+            for k in 0..4 {
+                a[col][k] /= p;
+                inv[col][k] /= p;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let f = a[row][col];
+                    for k in 0..4 {
+                        a[row][k] -= f * a[col][k];
+                        inv[row][k] -= f * inv[col][k];
+                    }
+                }
+            }
+        }
+        Some(Self { m: inv })
+    }
+
+    /// Determinant of the upper left 3x3 part, how much volumes scale.
+    pub fn linear_determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let row = |i: usize| m[i][0] * p.x() + m[i][1] * p.y() + m[i][2] * p.z() + m[i][3];
+        let w = row(3);
+        Vec3::new(row(0), row(1), row(2)) / w
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let row = |i: usize| m[i][0] * v.x() + m[i][1] * v.y() + m[i][2] * v.z();
+        Vec3::new(row(0), row(1), row(2))
+    }
+}
+
+impl ops::Mul<Matrix4> for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, e) in row.iter_mut().enumerate() {
+                *e = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Matrix4 { m }
+    }
+}
+
+/// A transform along with what hits need of it: the inverse to bring rays
+/// into object space, and the inverse transpose that keeps normals
+/// perpendicular to the surface on the way back out.
+#[derive(Clone, Copy)]
+struct Placement {
+    matrix: Matrix4,
+    inverse: Matrix4,
+    normal: Matrix4,
+}
+
+/// Places a shared object in the world through an affine transform.
+///
+/// The object is held by `Arc`, so many transforms can instance the same
+/// geometry without copying it.
+pub struct Transform {
+    placement: Placement,
+    hitable: Arc<dyn Hitable>,
+    bbox: AABB,
+}
+
+fn box_corners(b: &AABB) -> [Vec3; 8] {
+    let ends = [b.min, b.max];
+    let mut corners = [Vec3::default(); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        *corner = Vec3::new(ends[i & 1].x(), ends[(i >> 1) & 1].y(), ends[i >> 2].z());
+    }
+    corners
+}
+
+/// Smallest box around `points`, grown by `pad` on every side.
+fn bounds(points: impl Iterator<Item = Vec3>, pad: f32) -> AABB {
+    let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = Vec3::new(-f32::MAX, -f32::MAX, -f32::MAX);
+    for p in points {
+        min = Vec3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z()));
+        max = Vec3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z()));
+    }
+    let pad = Vec3::new(pad, pad, pad);
+    AABB::new(min - pad, max + pad)
+}
+
+impl Placement {
+    /// The ray in object space.
+    ///
+    /// The direction is not renormalized, so `t` means the same in both
+    /// spaces.
+    fn object_ray(&self, r: &Ray) -> Ray {
+        Ray::new(
+            self.inverse.transform_point(r.origin()),
+            self.inverse.transform_vector(r.direction()),
+            r.time,
+        )
+    }
+
+    /// Brings a hit in object space back out into the world.
+    fn world_record(&self, rec: HitRecord) -> HitRecord {
+        HitRecord {
+            p: self.matrix.transform_point(&rec.p),
+            normal: unit_vector(&self.normal.transform_vector(&rec.normal)),
+            ..rec
+        }
+    }
+
+    fn hit(
+        &self,
+        hitable: &dyn Hitable,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        hitable
+            .hit_sampled(&self.object_ray(r), t_min, t_max, sampler)
+            .map(|rec| self.world_record(rec))
+    }
+
+    fn crossings(&self, hitable: &dyn Hitable, r: &Ray) -> Vec<Crossing> {
+        hitable
+            .crossings(&self.object_ray(r))
+            .into_iter()
+            .map(|c| Crossing {
+                hit: self.world_record(c.hit),
+                entering: c.entering,
+            })
+            .collect()
+    }
+}
+
+impl Transform {
+    /// Panics if `matrix` is not invertible.
+    pub fn new(matrix: Matrix4, hitable: Arc<dyn Hitable>) -> Self {
+        let inverse = matrix.inverse().expect("singular transform");
+        let object_box = hitable.bounding_box(0.0, 1.0);
+        let bbox = if object_box.is_bounded() {
+            bounds(
+                box_corners(&object_box)
+                    .iter()
+                    .map(|c| matrix.transform_point(c)),
+                0.0,
+            )
+        } else {
+            AABB::infinite()
+        };
+        Self {
+            placement: Placement {
+                matrix,
+                inverse,
+                normal: inverse.transpose(),
+            },
+            hitable,
+            bbox,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Transform {
+    /// The same object placed by `matrix` instead.
+    pub fn moved(&self, matrix: Matrix4) -> Self {
+        Self::new(matrix, Arc::clone(&self.hitable))
+    }
+}
+
+impl Hitable for Transform {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.placement
+            .hit(self.hitable.as_ref(), r, t_min, t_max, sampler)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.hitable.is_solid()
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        self.placement.crossings(self.hitable.as_ref(), r)
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        self.bbox.clone()
+    }
+
+    fn is_light(&self) -> bool {
+        self.hitable.is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        let Placement { inverse, .. } = &self.placement;
+        let v = unit_vector(v);
+        let object_v = inverse.transform_vector(&v);
+        let length = object_v.lenght();
+        let object_pdf = self
+            .hitable
+            .pdf_value(&inverse.transform_point(o), &(object_v / length));
+        // Change of solid angle under the linear part of the inverse.
+        object_pdf * inverse.linear_determinant().abs() / (length * length * length)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let object_v = self
+            .hitable
+            .random(&self.placement.inverse.transform_point(o), sampler);
+        unit_vector(&self.placement.matrix.transform_vector(&object_v))
+    }
+}
+
+/// Unit quaternion, for interpolating rotations.
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion {
+    pub w: f32,
+    pub v: Vec3,
+}
+
+impl Quaternion {
+    pub fn identity() -> Self {
+        Self {
+            w: 1.0,
+            v: Vec3::default(),
+        }
+    }
+
+    /// Rotation by `angle` degrees counterclockwise around `axis`.
+    pub fn from_axis_angle(axis: &Vec3, angle: f32) -> Self {
+        let (sin, cos) = (0.5 * angle.to_radians()).sin_cos();
+        Self {
+            w: cos,
+            v: sin * unit_vector(axis),
+        }
+    }
+
+    fn dot(&self, other: &Self) -> f32 {
+        self.w * other.w + dot(&self.v, &other.v)
+    }
+
+    /// Angle in radians of the rotation taking `self` to `other`.
+    fn angle_to(&self, other: &Self) -> f32 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    /// Turns from `self` towards `other` at a constant rate, the short way
+    /// around.
+    pub fn slerp(&self, other: &Self, t: f32) -> Self {
+        let cos = self.dot(other);
+        let (cos, other) = if cos < 0.0 {
+            (
+                -cos,
+                Self {
+                    w: -other.w,
+                    v: -other.v,
+                },
+            )
+        } else {
+            (cos, *other)
+        };
+        let (a, b) = if 0.9995 < cos {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        let w = a * self.w + b * other.w;
+        let v = a * self.v + b * other.v;
+        let norm = (w * w + v.squared_length()).sqrt();
+        Self {
+            w: w / norm,
+            v: v / norm,
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        let (w, x, y, z) = (self.w, self.v.x(), self.v.y(), self.v.z());
+        Matrix4 {
+            m: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - w * z),
+                    2.0 * (x * z + w * y),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * y + w * z),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - w * x),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * z - w * y),
+                    2.0 * (y * z + w * x),
+                    1.0 - 2.0 * (x * x + y * y),
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+}
+
+/// Pose of an animated object at `time`: scaled, then rotated, then
+/// translated.
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+/// Moves a shared object through keyframes, for motion blur of anything.
+///
+/// Poses between keyframes interpolate translation and scale linearly and
+/// rotation by slerp; before the first and after the last keyframe the
+/// object holds still. The object is not sampled as a light, since light
+/// sampling does not know the time of the ray.
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+    hitable: Arc<dyn Hitable>,
+}
+
+impl AnimatedTransform {
+    /// Panics if there are no keyframes, or one scales an axis to zero or
+    /// mirrors it unlike the others, which would pass through zero scale in
+    /// between.
+    pub fn new(mut keyframes: Vec<Keyframe>, hitable: Arc<dyn Hitable>) -> Self {
+        assert!(!keyframes.is_empty(), "no keyframes");
+        let first = keyframes[0].scale;
+        assert!(
+            keyframes
+                .iter()
+                .all(|k| (0..3).all(|axis| 0.0 < k.scale[axis] * first[axis])),
+            "zero or sign changing scale"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keyframes, hitable }
+    }
+
+    /// The keyframes around `time` and how far along from the first to the
+    /// second it is.
+    fn segment(&self, time: f32) -> (&Keyframe, &Keyframe, f32) {
+        let keys = &self.keyframes;
+        let i = keys.partition_point(|k| k.time <= time);
+        if i == 0 {
+            (&keys[0], &keys[0], 0.0)
+        } else if i == keys.len() {
+            (&keys[i - 1], &keys[i - 1], 0.0)
+        } else {
+            let (a, b) = (&keys[i - 1], &keys[i]);
+            (a, b, (time - a.time) / (b.time - a.time))
+        }
+    }
+
+    fn pose(&self, time: f32) -> (Vec3, Quaternion, Vec3) {
+        let (a, b, s) = self.segment(time);
+        (
+            (1.0 - s) * a.translation + s * b.translation,
+            a.rotation.slerp(&b.rotation, s),
+            (1.0 - s) * a.scale + s * b.scale,
+        )
+    }
+
+    /// The transform at `time`. The inverse and the normals' matrix come
+    /// straight from the pose, without inverting or transposing.
+    fn placement(&self, time: f32) -> Placement {
+        let (translation, rotation, scale) = self.pose(time);
+        let r = rotation.matrix();
+        let inverse_scale = Matrix4::scale(&Vec3::new(
+            1.0 / scale.x(),
+            1.0 / scale.y(),
+            1.0 / scale.z(),
+        ));
+        Placement {
+            matrix: Matrix4::translate(&translation) * r * Matrix4::scale(&scale),
+            inverse: inverse_scale * r.transpose() * Matrix4::translate(&-translation),
+            // The inverse transpose of the linear part; normals ignore the
+            // translation.
+            normal: r * inverse_scale,
+        }
+    }
+}
+
+impl Hitable for AnimatedTransform {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_sampled(r, t_min, t_max, &mut Independent)
+    }
+
+    fn hit_sampled(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord> {
+        self.placement(r.time)
+            .hit(self.hitable.as_ref(), r, t_min, t_max, sampler)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.hitable.is_solid()
+    }
+
+    fn crossings(&self, r: &Ray) -> Vec<Crossing> {
+        self.placement(r.time).crossings(self.hitable.as_ref(), r)
+    }
+
+    /// Corners are followed at a few times between keyframes, and the box
+    /// is grown by how far they can stray from straight lines in between.
+    fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
+        const STEPS: usize = 16;
+        let object_box = self.hitable.bounding_box(t0, t1);
+        if !object_box.is_bounded() {
+            return AABB::infinite();
+        }
+        let corners = box_corners(&object_box);
+        let mut times = vec![t0];
+        times.extend(
+            self.keyframes
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| t0 < t && t < t1),
+        );
+        times.push(t1);
+
+        let mut bbox: Option<AABB> = None;
+        for span in times.windows(2) {
+            let (a, b) = (span[0], span[1]);
+            let h = (b - a) / STEPS as f32;
+            let (_, rotation_a, scale_a) = self.pose(a);
+            let (_, rotation_b, scale_b) = self.pose(b);
+            // Within a span the corner `p` moves as `t + R q` with `t` and
+            // `q = S p` linear in time and `R` turning at a constant rate
+            // `w`, so it curves by at most `w^2 |q| + 2 w |q'|`.
+            let pad = if 0.0 < h {
+                let w = rotation_a.angle_to(&rotation_b) / (b - a);
+                corners
+                    .iter()
+                    .map(|p| {
+                        let (qa, qb) = (scale_a * *p, scale_b * *p);
+                        let q = qa.lenght().max(qb.lenght());
+                        let dq = (qb - qa).lenght() / (b - a);
+                        h * h / 8.0 * (w * w * q + 2.0 * w * dq)
+                    })
+                    .fold(0.0, f32::max)
+            } else {
+                0.0
+            };
+            let points = (0..=STEPS).flat_map(|k| {
+                let matrix = self.placement(a + k as f32 * h).matrix;
+                corners
+                    .iter()
+                    .map(|c| matrix.transform_point(c))
+                    .collect::<Vec<_>>()
+            });
+            let span_box = bounds(points, pad);
+            bbox = Some(match bbox {
+                Some(b) => surrounding_box(&b, &span_box),
+                None => span_box,
+            });
+        }
+        bbox.unwrap()
+    }
+}
+
+#[test]
+fn test_transformed_sphere() {
+    use super::material::Lambertian;
+    use super::pdf::random_unit_vector;
+    use super::rng::reseed;
+    use super::sampler::Independent;
+    use super::sphere::Sphere;
+    use super::texture::ConstantTexture;
+
+    let m = Matrix4::translate(&Vec3::new(1.0, 2.0, 3.0))
+        * Matrix4::rotate(&Vec3::new(1.0, 1.0, 0.0), 40.0)
+        * Matrix4::scale(&Vec3::new(2.0, 1.0, 0.5));
+    let id = m * m.inverse().unwrap();
+    for (i, row) in id.m.iter().enumerate() {
+        for (j, e) in row.iter().enumerate() {
+            assert!((e - if i == j { 1.0 } else { 0.0 }).abs() < 1e-5);
+        }
+    }
+    assert!(Matrix4::scale(&Vec3::new(1.0, 0.0, 1.0))
+        .inverse()
+        .is_none());
+
+    // An ellipsoid stretched along x, shared by two instances.
+    let material = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(Vec3::new(
+        0.5, 0.5, 0.5,
+    )))));
+    let sphere: Arc<dyn Hitable> = Arc::new(Sphere::new(Vec3::default(), 1.0, material));
+    let stretched = Transform::new(
+        Matrix4::scale(&Vec3::new(3.0, 1.0, 1.0)),
+        Arc::clone(&sphere),
+    );
+    let moved = Transform::new(
+        Matrix4::translate(&Vec3::new(0.0, 5.0, 0.0)),
+        Arc::clone(&sphere),
+    );
+    assert_eq!(Arc::strong_count(&sphere), 3);
+
+    let r = Ray::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+    let rec = stretched.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 7.0).abs() < 1e-5 && (rec.p.x() - 3.0).abs() < 1e-5);
+    // A point where the plain normal and the inverse transpose disagree.
+    let r = Ray::new(Vec3::new(1.5, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = stretched.hit(&r, 0.001, f32::MAX).unwrap();
+    let n = rec.normal;
+    let expected = unit_vector(&Vec3::new(rec.p.x() / 9.0, rec.p.y(), rec.p.z()));
+    assert!((dot(&n, &expected) - 1.0).abs() < 1e-5, "{:?}", n);
+
+    let b = stretched.bounding_box(0.0, 1.0);
+    assert!((b.max.x() - 3.0).abs() < 1e-5 && (b.max.y() - 1.0).abs() < 1e-5);
+    assert!((moved.bounding_box(0.0, 1.0).min.y() - 4.0).abs() < 1e-5);
+
+    // Light sampling densities still integrate to one, and agree with the
+    // directions `random` returns.
+    reseed(9);
+    let o = Vec3::new(1.0, 4.0, 2.0);
+    let n = 200_000;
+    let integral = (0..n)
+        .map(|_| stretched.pdf_value(&o, &random_unit_vector()))
+        .sum::<f32>()
+        * 4.0
+        * std::f32::consts::PI
+        / n as f32;
+    assert!((integral - 1.0).abs() < 0.03, "integral {}", integral);
+    for _ in 0..100 {
+        let v = stretched.random(&o, &mut Independent);
+        assert!(0.0 < stretched.pdf_value(&o, &v));
+    }
+}
+
+#[test]
+fn test_animated_transform() {
+    use std::f32::consts::PI;
+
+    use super::box_::Box_;
+    use super::material::Lambertian;
+    use super::texture::ConstantTexture;
+
+    let material = Arc::new(Lambertian::new(Box::new(ConstantTexture::new(Vec3::new(
+        0.5, 0.5, 0.5,
+    )))));
+    let cube: Arc<dyn Hitable> = Arc::new(Box_::new(
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(1.0, 1.0, 1.0),
+        material,
+    ));
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let key = |time, x, angle, scale| Keyframe {
+        time,
+        translation: Vec3::new(x, 0.0, 0.0),
+        rotation: Quaternion::from_axis_angle(&up, angle),
+        scale: Vec3::new(scale, 1.0, 1.0),
+    };
+    // Slides along x while turning a quarter and stretching, then stops.
+    let animated = AnimatedTransform::new(
+        vec![key(0.5, 10.0, 90.0, 3.0), key(0.0, 0.0, 0.0, 1.0)],
+        cube,
+    );
+
+    // Halfway through, the cube is at x = 5 and turned by 45 degrees.
+    let r = Ray::new(Vec3::new(5.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.25);
+    let rec = animated.hit(&r, 0.001, f32::MAX).unwrap();
+    let (_, rotation, _) = animated.pose(0.25);
+    assert!((rotation.angle_to(&Quaternion::identity()) - PI / 4.0).abs() < 1e-4);
+    let Placement {
+        matrix,
+        inverse,
+        normal,
+    } = animated.placement(0.25);
+    let id = matrix * inverse;
+    let transposed = inverse.transpose();
+    for i in 0..3 {
+        for j in 0..3 {
+            assert!((normal.m[i][j] - transposed.m[i][j]).abs() < 1e-6);
+        }
+    }
+    assert!((id.m[0][0] - 1.0).abs() < 1e-5 && id.m[0][3].abs() < 1e-4);
+    let q = inverse.transform_point(&rec.p);
+    assert!((q.x().abs().max(q.y().abs()).max(q.z().abs()) - 1.0).abs() < 1e-4);
+    // Past the last keyframe it holds still.
+    let late = Ray::new(Vec3::new(10.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.9);
+    let rec = animated.hit(&late, 0.001, f32::MAX).unwrap();
+    assert!((rec.p.z() - 3.0).abs() < 1e-4, "{:?}", rec.p);
+
+    // The box covers every corner at every time, without being much bigger
+    // than the swept volume.
+    let bbox = animated.bounding_box(0.0, 1.0);
+    for k in 0..=1000 {
+        let matrix = animated.placement(k as f32 / 1000.0).matrix;
+        for c in box_corners(&AABB::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        )) {
+            let p = matrix.transform_point(&c);
+            for a in 0..3 {
+                assert!(bbox.min[a] <= p[a] && p[a] <= bbox.max[a], "{:?}", p);
+            }
+        }
+    }
+    assert!(-3.5 < bbox.min.x() && bbox.max.x() < 12.0, "{:?}", bbox);
+    assert!(-4.0 < bbox.min.z() && bbox.max.z() < 4.0, "{:?}", bbox);
+}
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use super::aabb::AABB;
+use super::hitable::{HitRecord, Hitable};
+use super::hitable_list::HitableList;
+use super::material::Material;
+use super::ray::Ray;
+use super::sampler::Sampler;
+use super::vec3::*;
+
+/// Vertex and index buffers shared by every triangle of a mesh.
+struct MeshData {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    texcoords: Option<Vec<[f32; 2]>>,
+    indices: Vec<[usize; 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl MeshData {
+    fn new(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        texcoords: Option<Vec<[f32; 2]>>,
+        indices: Vec<[usize; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        if let Some(normals) = &normals {
+            assert_eq!(
+                normals.len(),
+                positions.len(),
+                "a mesh needs one normal per vertex"
+            );
+        }
+        if let Some(texcoords) = &texcoords {
+            assert_eq!(
+                texcoords.len(),
+                positions.len(),
+                "a mesh needs one texture coordinate per vertex"
+            );
+        }
+        assert!(
+            indices.iter().flatten().all(|&i| i < positions.len()),
+            "mesh index out of range"
+        );
+        Self {
+            positions,
+            normals,
+            texcoords,
+            indices,
+            material,
+        }
+    }
+}
+
+/// Möller–Trumbore, returning `t` and the weights of `p1` and `p2`.
+pub(crate) fn hit_triangle(r: &Ray, [p0, p1, p2]: [Vec3; 3]) -> Option<(f32, f32, f32)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let pvec = cross(r.direction(), &e2);
+    let det = dot(&e1, &pvec);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = *r.origin() - p0;
+    let u = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let qvec = cross(&tvec, &e1);
+    let v = dot(r.direction(), &qvec) * inv_det;
+    if v < 0.0 || 1.0 < u + v {
+        return None;
+    }
+    Some((dot(&e2, &qvec) * inv_det, u, v))
+}
+
+/// One face of a mesh.
+///
+/// `u` and `v` of a hit are the barycentric weights of the second and third
+/// vertex, unless the mesh has texture coordinates to interpolate. Vertex
+/// normals are interpolated the same way, otherwise the geometric normal is
+/// used.
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl Triangle {
+    pub fn new(v0: Vec3, v1: Vec3, v2: Vec3, material: Arc<dyn Material>) -> Self {
+        Self {
+            mesh: Arc::new(MeshData::new(
+                vec![v0, v1, v2],
+                None,
+                None,
+                vec![[0, 1, 2]],
+                material,
+            )),
+            face: 0,
+        }
+    }
+
+    pub fn with_normals(
+        vertices: [Vec3; 3],
+        normals: [Vec3; 3],
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            mesh: Arc::new(MeshData::new(
+                vertices.to_vec(),
+                Some(normals.to_vec()),
+                None,
+                vec![[0, 1, 2]],
+                material,
+            )),
+            face: 0,
+        }
+    }
+
+    fn vertices(&self) -> [Vec3; 3] {
+        let [i0, i1, i2] = self.mesh.indices[self.face];
+        let p = &self.mesh.positions;
+        [p[i0], p[i1], p[i2]]
+    }
+}
+
+impl Hitable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let [p0, p1, p2] = self.vertices();
+        let (t, u, v) = hit_triangle(r, [p0, p1, p2])?;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let [i0, i1, i2] = self.mesh.indices[self.face];
+        let w = 1.0 - u - v;
+        let normal = match &self.mesh.normals {
+            Some(normals) => unit_vector(&(w * normals[i0] + u * normals[i1] + v * normals[i2])),
+            None => unit_vector(&cross(&(p1 - p0), &(p2 - p0))),
+        };
+        let (tex_u, tex_v) = match &self.mesh.texcoords {
+            Some(uv) => (
+                w * uv[i0][0] + u * uv[i1][0] + v * uv[i2][0],
+                w * uv[i0][1] + u * uv[i1][1] + v * uv[i2][1],
+            ),
+            None => (u, v),
+        };
+        Some(HitRecord {
+            t,
+            p: r.point_at_parameter(t),
+            u: tex_u,
+            v: tex_v,
+            normal,
+            material: Arc::clone(&self.mesh.material),
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> AABB {
+        let [p0, p1, p2] = self.vertices();
+        let mut min = [0.0; 3];
+        let mut max = [0.0; 3];
+        for a in 0..3 {
+            min[a] = p0[a].min(p1[a]).min(p2[a]);
+            max[a] = p0[a].max(p1[a]).max(p2[a]);
+            // Keep axis-aligned triangles from producing a flat box.
+            if max[a] - min[a] < 0.0002 {
+                min[a] -= 0.0001;
+                max[a] += 0.0001;
+            }
+        }
+        AABB::new(
+            Vec3::new(min[0], min[1], min[2]),
+            Vec3::new(max[0], max[1], max[2]),
+        )
+    }
+
+    fn is_light(&self) -> bool {
+        self.mesh.material.is_emissive()
+    }
+
+    /// Like `planar_pdf_value`, but with the cosine taken against the face
+    /// rather than the interpolated normal a hit carries.
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        let rec = match self.hit(&Ray::new(*o, *v, 0.0), 0.001, f32::MAX) {
+            Some(rec) => rec,
+            None => return 0.0,
+        };
+        let [p0, p1, p2] = self.vertices();
+        let face = cross(&(p1 - p0), &(p2 - p0));
+        let area = 0.5 * face.lenght();
+        let distance_squared = rec.t * rec.t * v.squared_length();
+        let cosine = (dot(v, &face) / (v.lenght() * face.lenght())).abs();
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        // Uniform over the face, see Osada et al., "Shape Distributions".
+        let [p0, p1, p2] = self.vertices();
+        let (s, r) = sampler.next_2d();
+        let s = s.sqrt();
+        let p = (1.0 - s) * p0 + s * (1.0 - r) * p1 + s * r * p2;
+        p - *o
+    }
+}
+
+/// Triangles sharing one set of buffers, with their own BVH.
+pub struct TriangleMesh(HitableList);
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        texcoords: Option<Vec<[f32; 2]>>,
+        indices: Vec<[usize; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let mesh = Arc::new(MeshData::new(
+            positions, normals, texcoords, indices, material,
+        ));
+        let triangles = (0..mesh.indices.len())
+            .map(|face| {
+                Box::new(Triangle {
+                    mesh: Arc::clone(&mesh),
+                    face,
+                }) as Box<dyn Hitable>
+            })
+            .collect::<HitableList>();
+        Self(triangles)
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.0.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> AABB {
+        self.0.bounding_box(t0, t1)
+    }
+
+    fn is_light(&self) -> bool {
+        self.0.is_light()
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        self.0.pdf_value(o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.0.random(o, sampler)
+    }
+}
+
+#[test]
+fn test_triangle_hit() {
+    use super::material::Dielectric;
+
+    let material: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let triangle = Triangle::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::clone(&material),
+    );
+    let r = Ray::new(Vec3::new(0.25, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    let rec = triangle.hit(&r, 0.0, 10.0).unwrap();
+    assert!((rec.t - 1.0).abs() < 1e-6);
+    assert!((rec.u - 0.25).abs() < 1e-6);
+    assert!((rec.v - 0.5).abs() < 1e-6);
+    assert!((rec.normal.z() - 1.0).abs() < 1e-6);
+
+    let miss = Ray::new(Vec3::new(0.75, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    assert!(triangle.hit(&miss, 0.0, 10.0).is_none());
+
+    let smooth = Triangle::with_normals(
+        [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ],
+        [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ],
+        material,
+    );
+    let rec = smooth.hit(&r, 0.0, 10.0).unwrap();
+    let expected = unit_vector(&Vec3::new(0.25, 0.0, 0.75));
+    assert!((rec.normal - expected).lenght() < 1e-6);
+
+    // Seen head on from a unit away, the face's area of 1/2 covers a solid
+    // angle of about 1/2, whichever way its shading normals lean.
+    let o = Vec3::new(0.25, 0.5, 1.0);
+    let v = Vec3::new(0.0, 0.0, -1.0);
+    assert!((triangle.pdf_value(&o, &v) - 2.0).abs() < 1e-5);
+    assert!((smooth.pdf_value(&o, &v) - 2.0).abs() < 1e-5);
+}
@@ -1,4 +1,5 @@
 use std::ops;
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Vec3([f32; 3]);
@@ -41,6 +42,22 @@ impl Vec3 {
     }
 }
 
+impl FromStr for Vec3 {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let e = s
+            .split(',')
+            .map(|c| c.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("invalid vector `{}`: {}", s, err))?;
+        match e[..] {
+            [e0, e1, e2] => Ok(Self::new(e0, e1, e2)),
+            _ => Err(format!("invalid vector `{}`: expected x,y,z", s)),
+        }
+    }
+}
+
 impl ops::Neg for Vec3 {
     type Output = Vec3;
 
@@ -178,7 +195,7 @@ impl ops::Div<f32> for Vec3 {
 }
 
 pub fn unit_vector(v: &Vec3) -> Vec3 {
-    let mut tmp = v.clone();
+    let mut tmp = *v;
     tmp.make_unit_vector();
     tmp
 }
@@ -204,6 +221,17 @@ fn test_nen() {
     assert_eq!(n_vec.z(), -2.0);
 }
 
+#[test]
+fn test_from_str() {
+    let vec = "0.5, -1,2".parse::<Vec3>().unwrap();
+    assert_eq!(vec.x(), 0.5);
+    assert_eq!(vec.y(), -1.0);
+    assert_eq!(vec.z(), 2.0);
+
+    assert!("1,2".parse::<Vec3>().is_err());
+    assert!("1,2,x".parse::<Vec3>().is_err());
+}
+
 #[test]
 fn test_index() {
     let vec = Vec3::new(0.0, 1.0, 2.0);
@@ -333,4 +361,4 @@ fn test_cross() {
     assert_eq!(c.x(), -3.0);
     assert_eq!(c.y(), 6.0);
     assert_eq!(c.z(), -3.0);
-}
\ No newline at end of file
+}